@@ -0,0 +1,837 @@
+use crate::handlers::{MultiId, TxKey};
+use crate::traits::{StorageWrite, TxStorage};
+use parity_scale_codec::{Decode, Encode};
+use primitives::TxId;
+use serde_json::Value as JsonValue;
+use sled::transaction::{TransactionResult, Transactional};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+/// Entry of the simulation queue
+/// `leased_until` is the unix time in millis until which the entry is handed to a consumer
+/// waiting for its acknowledgement, once passed the entry is delivered again
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct SimulationEntry {
+    pub tx_simulation: Vec<u8>,
+    pub leased_until: u64,
+}
+
+/// A mock database storing each address to the transactions each having a key
+/// `address` ===> `TxKey`=====> `Vec<u8>`
+/// Everything is lost once the server stops, use `SledDB` for persistence
+#[derive(Default)]
+pub struct MockDB {
+    // ============================================================================
+    // DB_DATA
+
+    // Map of tx key ( multi_id + tx_id ) to encoded transactions
+    pub transactions: BTreeMap<TxKey, Vec<u8>>,
+    // Index of the stored transactions by tx id
+    pub tx_ids: BTreeMap<TxId, TxKey>,
+    // Transactions in their confirmation phase ordered by the end of their confirmation window
+    pub expiry: BTreeSet<(u64, TxKey)>,
+    // Map of account id per user ( sender | receiver ) to array of tx keys ( indicating pending transactions)
+    pub multi_ids: BTreeMap<MultiId, Vec<TxKey>>,
+    // Map to store confirmation phase of transactions
+    // `tx key` to `TxConfrimationObject`
+    pub confirmation: BTreeMap<TxKey, Vec<u8>>,
+    // Store ready to be simulated tx `TxSimulationObject` (queue)
+    // ordered by the delivery id given on insertion
+    pub simulation: BTreeMap<u64, SimulationEntry>,
+    // Last delivery id given to a simulation queue entry
+    pub simulation_id: u64,
+    // Record reverted transactions per sender
+    pub reverted_transactions: BTreeMap<MultiId, Vec<Vec<u8>>>,
+    // Number of confirmations per transaction which failed signature verification
+    pub failed_confirmations: BTreeMap<TxKey, u32>,
+    // Latest `TxExecutionStatus` reported by the router per transaction
+    pub execution_status: BTreeMap<TxKey, Vec<u8>>,
+    // Receivers each sender completed the confirmation flow with
+    pub counterparties: BTreeMap<MultiId, Vec<Vec<u8>>>,
+
+    // ============================================================================
+    // METRICS
+
+    // Keep track of subscibed clients "id"
+    pub subscribed: Vec<JsonValue>,
+}
+
+impl TxStorage for MockDB {
+    fn set_transaction(&mut self, tx_key: TxKey, tx: Vec<u8>) -> anyhow::Result<()> {
+        self.tx_ids.insert(tx_key.tx_id.clone(), tx_key.clone());
+        self.transactions.insert(tx_key, tx);
+        Ok(())
+    }
+
+    fn get_transaction(&self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.transactions.get(tx_key).cloned())
+    }
+
+    fn remove_transaction(&mut self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>> {
+        self.tx_ids.remove(&tx_key.tx_id);
+        Ok(self.transactions.remove(tx_key))
+    }
+
+    fn find_tx_key(&self, tx_id: &TxId) -> anyhow::Result<Option<TxKey>> {
+        Ok(self.tx_ids.get(tx_id).cloned())
+    }
+
+    fn expiring_transactions(&self, now: u64) -> anyhow::Result<Vec<(u64, TxKey)>> {
+        Ok(self
+            .expiry
+            .iter()
+            .take_while(|(expires_at, _)| *expires_at <= now)
+            .cloned()
+            .collect())
+    }
+
+    fn apply(&mut self, writes: Vec<StorageWrite>) -> anyhow::Result<()> {
+        for write in writes {
+            match write {
+                StorageWrite::SetTransaction(tx_key, tx) => self.set_transaction(tx_key, tx)?,
+                StorageWrite::SetMultiIds(account, multi_ids) => {
+                    self.set_multi_ids(account, multi_ids)?
+                }
+                StorageWrite::SetConfirmation(tx_key, tx_confirmation) => {
+                    self.set_confirmation(tx_key, tx_confirmation)?
+                }
+                StorageWrite::PushSimulation(tx_simulation) => {
+                    self.push_simulation(tx_simulation)?
+                }
+                StorageWrite::IndexExpiry(expires_at, tx_key) => {
+                    self.expiry.insert((expires_at, tx_key));
+                }
+                StorageWrite::UnindexExpiry(expires_at, tx_key) => {
+                    self.expiry.remove(&(expires_at, tx_key));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn set_multi_ids(&mut self, account: MultiId, multi_ids: Vec<TxKey>) -> anyhow::Result<()> {
+        self.multi_ids.insert(account, multi_ids);
+        Ok(())
+    }
+
+    fn get_multi_ids(&self, account: &MultiId) -> anyhow::Result<Option<Vec<TxKey>>> {
+        Ok(self.multi_ids.get(account).cloned())
+    }
+
+    fn set_confirmation(&mut self, tx_key: TxKey, tx_confirmation: Vec<u8>) -> anyhow::Result<()> {
+        self.confirmation.insert(tx_key, tx_confirmation);
+        Ok(())
+    }
+
+    fn get_confirmation(&self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.confirmation.get(tx_key).cloned())
+    }
+
+    fn remove_confirmation(&mut self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.confirmation.remove(tx_key))
+    }
+
+    fn push_simulation(&mut self, tx_simulation: Vec<u8>) -> anyhow::Result<()> {
+        self.simulation_id += 1;
+        self.simulation.insert(
+            self.simulation_id,
+            SimulationEntry {
+                tx_simulation,
+                leased_until: 0,
+            },
+        );
+        Ok(())
+    }
+
+    fn lease_simulation(&mut self, now: u64, lease: u64) -> anyhow::Result<Option<(u64, Vec<u8>)>> {
+        Ok(self
+            .simulation
+            .iter_mut()
+            .find(|(_, entry)| entry.leased_until <= now)
+            .map(|(id, entry)| {
+                entry.leased_until = now + lease;
+                (*id, entry.tx_simulation.clone())
+            }))
+    }
+
+    fn ack_simulation(&mut self, id: u64) -> anyhow::Result<bool> {
+        Ok(self.simulation.remove(&id).is_some())
+    }
+
+    fn is_simulation_queued(&self, id: u64) -> anyhow::Result<bool> {
+        Ok(self.simulation.contains_key(&id))
+    }
+
+    #[cfg(test)]
+    fn simulation_len(&self) -> anyhow::Result<usize> {
+        Ok(self.simulation.len())
+    }
+
+    fn contains_simulation(&self, find: &dyn Fn(&[u8]) -> bool) -> anyhow::Result<bool> {
+        Ok(self
+            .simulation
+            .values()
+            .any(|entry| find(&entry.tx_simulation)))
+    }
+
+    fn remove_simulation(&mut self, remove: &dyn Fn(&[u8]) -> bool) -> anyhow::Result<usize> {
+        let before = self.simulation.len();
+        self.simulation
+            .retain(|_, entry| !remove(&entry.tx_simulation));
+        Ok(before - self.simulation.len())
+    }
+
+    fn record_reverted(&mut self, sender: MultiId, tx: Vec<u8>) -> anyhow::Result<()> {
+        self.reverted_transactions
+            .entry(sender)
+            .or_default()
+            .push(tx);
+        Ok(())
+    }
+
+    fn get_reverted(&self, sender: &MultiId) -> anyhow::Result<Vec<Vec<u8>>> {
+        Ok(self
+            .reverted_transactions
+            .get(sender)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn record_failed_confirmation(&mut self, tx_key: &TxKey) -> anyhow::Result<u32> {
+        let failed = self.failed_confirmations.entry(tx_key.clone()).or_default();
+        *failed += 1;
+        Ok(*failed)
+    }
+
+    fn get_failed_confirmations(&self, tx_key: &TxKey) -> anyhow::Result<u32> {
+        Ok(self
+            .failed_confirmations
+            .get(tx_key)
+            .copied()
+            .unwrap_or_default())
+    }
+
+    fn set_execution_status(&mut self, tx_key: TxKey, status: Vec<u8>) -> anyhow::Result<()> {
+        self.execution_status.insert(tx_key, status);
+        Ok(())
+    }
+
+    fn get_execution_status(&self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.execution_status.get(tx_key).cloned())
+    }
+
+    fn set_counterparties(
+        &mut self,
+        sender: MultiId,
+        counterparties: Vec<Vec<u8>>,
+    ) -> anyhow::Result<()> {
+        self.counterparties.insert(sender, counterparties);
+        Ok(())
+    }
+
+    fn get_counterparties(&self, sender: &MultiId) -> anyhow::Result<Vec<Vec<u8>>> {
+        Ok(self.counterparties.get(sender).cloned().unwrap_or_default())
+    }
+
+    fn record_subscriber(&mut self, id: JsonValue) -> anyhow::Result<()> {
+        self.subscribed.push(id);
+        self.subscribed.dedup();
+        Ok(())
+    }
+}
+
+/// Layout version of the values stored by `SledDB`
+/// bumped on every change of the encoding of the stored objects
+pub const STORAGE_VERSION: u32 = 2;
+const STORAGE_VERSION_KEY: &[u8] = b"storage_version";
+
+/// Embedded on-disk database backed by `sled`
+/// Each `MockDB` map lives in its own tree and every write is flushed
+/// so pending confirmations survive restarts and crashes of the server
+pub struct SledDB {
+    db: sled::Db,
+    // `storage_version` ===> encoded `u32` layout version of the database
+    meta: sled::Tree,
+    // encoded `TxKey` ===> encoded `TxObject`
+    transactions: sled::Tree,
+    // encoded `TxId` ===> encoded `TxKey`
+    tx_ids: sled::Tree,
+    // big endian `u64` expiry time followed by the encoded `TxKey` ===> empty
+    expiry: sled::Tree,
+    // `address` ===> encoded `Vec<TxKey>`
+    multi_ids: sled::Tree,
+    // encoded `TxKey` ===> encoded `TxConfirmationObject`
+    confirmation: sled::Tree,
+    // monotonic big endian `u64` delivery id ===> encoded `SimulationEntry`
+    simulation: sled::Tree,
+    // `sender` ===> encoded `Vec` of reverted `TxObject`
+    reverted_transactions: sled::Tree,
+    // encoded `TxKey` ===> encoded `u32` failed confirmations
+    failed_confirmations: sled::Tree,
+    // encoded `TxKey` ===> encoded `TxExecutionStatus`
+    execution_status: sled::Tree,
+    // `sender` ===> encoded `Vec` of encoded `KnownCounterparty`
+    counterparties: sled::Tree,
+    // subscription id as json string ===> empty
+    subscribed: sled::Tree,
+}
+
+impl SledDB {
+    /// Open the database at `path`, refusing databases written with another `STORAGE_VERSION`
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let db = sled::open(path)?;
+        let sled_db = Self {
+            meta: db.open_tree("meta")?,
+            transactions: db.open_tree("transactions")?,
+            tx_ids: db.open_tree("tx_ids")?,
+            expiry: db.open_tree("expiry")?,
+            multi_ids: db.open_tree("multi_ids")?,
+            confirmation: db.open_tree("confirmation")?,
+            simulation: db.open_tree("simulation")?,
+            reverted_transactions: db.open_tree("reverted_transactions")?,
+            failed_confirmations: db.open_tree("failed_confirmations")?,
+            execution_status: db.open_tree("execution_status")?,
+            counterparties: db.open_tree("counterparties")?,
+            subscribed: db.open_tree("subscribed")?,
+            db,
+        };
+        sled_db.check_version()?;
+        Ok(sled_db)
+    }
+
+    fn check_version(&self) -> anyhow::Result<()> {
+        if let Some(version) = self.meta.get(STORAGE_VERSION_KEY)? {
+            let version = u32::decode(&mut &version[..])?;
+            if version != STORAGE_VERSION {
+                anyhow::bail!(
+                    "Database layout version {version} is incompatible with version {STORAGE_VERSION}"
+                );
+            }
+            return Ok(());
+        }
+        // data written before the layout was versioned can not be decoded safely
+        let is_empty = [
+            &self.transactions,
+            &self.tx_ids,
+            &self.expiry,
+            &self.multi_ids,
+            &self.confirmation,
+            &self.simulation,
+            &self.reverted_transactions,
+            &self.failed_confirmations,
+            &self.execution_status,
+            &self.counterparties,
+        ]
+        .iter()
+        .all(|tree| tree.is_empty());
+        if !is_empty {
+            anyhow::bail!("Database has no layout version, expected version {STORAGE_VERSION}");
+        }
+        self.meta
+            .insert(STORAGE_VERSION_KEY, STORAGE_VERSION.encode())?;
+        self.flush()
+    }
+
+    fn flush(&self) -> anyhow::Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+/// Key of the expiry index entry, big endian keeps the tree ordered by expiry time
+fn expiry_key(expires_at: u64, tx_key: &TxKey) -> Vec<u8> {
+    [&expires_at.to_be_bytes()[..], &tx_key.encode()].concat()
+}
+
+impl TxStorage for SledDB {
+    fn set_transaction(&mut self, tx_key: TxKey, tx: Vec<u8>) -> anyhow::Result<()> {
+        self.apply(vec![StorageWrite::SetTransaction(tx_key, tx)])
+    }
+
+    fn get_transaction(&self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self
+            .transactions
+            .get(tx_key.encode())?
+            .map(|tx| tx.to_vec()))
+    }
+
+    fn remove_transaction(&mut self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>> {
+        let removed: TransactionResult<_, sled::Error> = (&self.transactions, &self.tx_ids)
+            .transaction(|(transactions, tx_ids)| {
+                tx_ids.remove(tx_key.tx_id.encode())?;
+                Ok(transactions.remove(tx_key.encode())?)
+            });
+        let tx = removed?.map(|tx| tx.to_vec());
+        self.flush()?;
+        Ok(tx)
+    }
+
+    fn find_tx_key(&self, tx_id: &TxId) -> anyhow::Result<Option<TxKey>> {
+        if let Some(tx_key) = self.tx_ids.get(tx_id.encode())? {
+            Ok(Some(TxKey::decode(&mut &tx_key[..])?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn expiring_transactions(&self, now: u64) -> anyhow::Result<Vec<(u64, TxKey)>> {
+        let mut due = Vec::new();
+        for entry in self.expiry.range(..now.saturating_add(1).to_be_bytes()) {
+            let (key, _) = entry?;
+            let expires_at = u64::from_be_bytes(key[..8].try_into()?);
+            due.push((expires_at, TxKey::decode(&mut &key[8..])?));
+        }
+        Ok(due)
+    }
+
+    fn apply(&mut self, writes: Vec<StorageWrite>) -> anyhow::Result<()> {
+        let trees = (
+            &self.transactions,
+            &self.tx_ids,
+            &self.expiry,
+            &self.multi_ids,
+            &self.confirmation,
+            &self.simulation,
+        );
+        let applied: TransactionResult<(), sled::Error> = trees.transaction(
+            |(transactions, tx_ids, expiry, multi_ids, confirmation, simulation)| {
+                for write in &writes {
+                    match write {
+                        StorageWrite::SetTransaction(tx_key, tx) => {
+                            tx_ids.insert(tx_key.tx_id.encode(), tx_key.encode())?;
+                            transactions.insert(tx_key.encode(), tx.clone())?;
+                        }
+                        StorageWrite::IndexExpiry(expires_at, tx_key) => {
+                            expiry.insert(expiry_key(*expires_at, tx_key), vec![])?;
+                        }
+                        StorageWrite::UnindexExpiry(expires_at, tx_key) => {
+                            expiry.remove(expiry_key(*expires_at, tx_key))?;
+                        }
+                        StorageWrite::SetMultiIds(account, keys) => {
+                            multi_ids.insert(account.encode(), keys.encode())?;
+                        }
+                        StorageWrite::SetConfirmation(tx_key, tx_confirmation) => {
+                            confirmation.insert(tx_key.encode(), tx_confirmation.clone())?;
+                        }
+                        StorageWrite::PushSimulation(tx_simulation) => {
+                            // ids of a retried transaction are skipped, the order is kept
+                            let key = simulation.generate_id()?.to_be_bytes();
+                            let entry = SimulationEntry {
+                                tx_simulation: tx_simulation.clone(),
+                                leased_until: 0,
+                            };
+                            simulation.insert(&key, entry.encode())?;
+                        }
+                    }
+                }
+                Ok(())
+            },
+        );
+        applied?;
+        self.flush()
+    }
+
+    fn set_multi_ids(&mut self, account: MultiId, multi_ids: Vec<TxKey>) -> anyhow::Result<()> {
+        self.multi_ids
+            .insert(account.encode(), multi_ids.encode())?;
+        self.flush()
+    }
+
+    fn get_multi_ids(&self, account: &MultiId) -> anyhow::Result<Option<Vec<TxKey>>> {
+        if let Some(multi_ids) = self.multi_ids.get(account.encode())? {
+            let multi_ids: Vec<TxKey> = Decode::decode(&mut &multi_ids[..])?;
+            Ok(Some(multi_ids))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn set_confirmation(&mut self, tx_key: TxKey, tx_confirmation: Vec<u8>) -> anyhow::Result<()> {
+        self.confirmation.insert(tx_key.encode(), tx_confirmation)?;
+        self.flush()
+    }
+
+    fn get_confirmation(&self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self
+            .confirmation
+            .get(tx_key.encode())?
+            .map(|tx_confirmation| tx_confirmation.to_vec()))
+    }
+
+    fn remove_confirmation(&mut self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>> {
+        let tx_confirmation = self
+            .confirmation
+            .remove(tx_key.encode())?
+            .map(|tx_confirmation| tx_confirmation.to_vec());
+        self.flush()?;
+        Ok(tx_confirmation)
+    }
+
+    fn push_simulation(&mut self, tx_simulation: Vec<u8>) -> anyhow::Result<()> {
+        // big endian keeps the tree ordered by insertion
+        let key = self.db.generate_id()?.to_be_bytes();
+        let entry = SimulationEntry {
+            tx_simulation,
+            leased_until: 0,
+        };
+        self.simulation.insert(key, entry.encode())?;
+        self.flush()
+    }
+
+    fn lease_simulation(&mut self, now: u64, lease: u64) -> anyhow::Result<Option<(u64, Vec<u8>)>> {
+        for entry in self.simulation.iter() {
+            let (key, value) = entry?;
+            let mut entry = SimulationEntry::decode(&mut &value[..])?;
+            if entry.leased_until <= now {
+                entry.leased_until = now + lease;
+                self.simulation.insert(&key, entry.encode())?;
+                self.flush()?;
+                let id = u64::from_be_bytes(key.as_ref().try_into()?);
+                return Ok(Some((id, entry.tx_simulation)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn ack_simulation(&mut self, id: u64) -> anyhow::Result<bool> {
+        let acked = self.simulation.remove(id.to_be_bytes())?.is_some();
+        self.flush()?;
+        Ok(acked)
+    }
+
+    fn is_simulation_queued(&self, id: u64) -> anyhow::Result<bool> {
+        Ok(self.simulation.contains_key(id.to_be_bytes())?)
+    }
+
+    #[cfg(test)]
+    fn simulation_len(&self) -> anyhow::Result<usize> {
+        Ok(self.simulation.len())
+    }
+
+    fn contains_simulation(&self, find: &dyn Fn(&[u8]) -> bool) -> anyhow::Result<bool> {
+        for entry in self.simulation.iter() {
+            let (_, value) = entry?;
+            let entry = SimulationEntry::decode(&mut &value[..])?;
+            if find(&entry.tx_simulation) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn remove_simulation(&mut self, remove: &dyn Fn(&[u8]) -> bool) -> anyhow::Result<usize> {
+        let mut removed = 0;
+        for entry in self.simulation.iter() {
+            let (key, value) = entry?;
+            let entry = SimulationEntry::decode(&mut &value[..])?;
+            if remove(&entry.tx_simulation) {
+                self.simulation.remove(key)?;
+                removed += 1;
+            }
+        }
+        self.flush()?;
+        Ok(removed)
+    }
+
+    fn record_reverted(&mut self, sender: MultiId, tx: Vec<u8>) -> anyhow::Result<()> {
+        let mut reverted = self.get_reverted(&sender)?;
+        reverted.push(tx);
+        self.reverted_transactions
+            .insert(sender.encode(), reverted.encode())?;
+        self.flush()
+    }
+
+    fn get_reverted(&self, sender: &MultiId) -> anyhow::Result<Vec<Vec<u8>>> {
+        if let Some(reverted) = self.reverted_transactions.get(sender.encode())? {
+            Ok(Decode::decode(&mut &reverted[..])?)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    fn record_failed_confirmation(&mut self, tx_key: &TxKey) -> anyhow::Result<u32> {
+        let failed = self.get_failed_confirmations(tx_key)? + 1;
+        self.failed_confirmations
+            .insert(tx_key.encode(), failed.encode())?;
+        self.flush()?;
+        Ok(failed)
+    }
+
+    fn get_failed_confirmations(&self, tx_key: &TxKey) -> anyhow::Result<u32> {
+        if let Some(failed) = self.failed_confirmations.get(tx_key.encode())? {
+            Ok(Decode::decode(&mut &failed[..])?)
+        } else {
+            Ok(0)
+        }
+    }
+
+    fn set_execution_status(&mut self, tx_key: TxKey, status: Vec<u8>) -> anyhow::Result<()> {
+        self.execution_status.insert(tx_key.encode(), status)?;
+        self.flush()
+    }
+
+    fn get_execution_status(&self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self
+            .execution_status
+            .get(tx_key.encode())?
+            .map(|status| status.to_vec()))
+    }
+
+    fn set_counterparties(
+        &mut self,
+        sender: MultiId,
+        counterparties: Vec<Vec<u8>>,
+    ) -> anyhow::Result<()> {
+        self.counterparties
+            .insert(sender.encode(), counterparties.encode())?;
+        self.flush()
+    }
+
+    fn get_counterparties(&self, sender: &MultiId) -> anyhow::Result<Vec<Vec<u8>>> {
+        if let Some(counterparties) = self.counterparties.get(sender.encode())? {
+            Ok(Decode::decode(&mut &counterparties[..])?)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    fn record_subscriber(&mut self, id: JsonValue) -> anyhow::Result<()> {
+        self.subscribed.insert(id.to_string(), Vec::<u8>::new())?;
+        self.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitives::{BlockchainNetwork, TxObject, VaneCallData};
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use subxt::utils::{AccountId32, MultiAddress};
+
+    /// Fresh database directory removed once the test is done
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new() -> Self {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos();
+            Self(std::env::temp_dir().join(format!("vane-av-db-{}-{nanos}", std::process::id())))
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Open the database at `path` again once sled let go of the previous handle
+    /// the file lock is released by a background thread shortly after the handle is dropped
+    fn reopen(path: &TempPath) -> anyhow::Result<SledDB> {
+        let mut attempts = 0;
+        loop {
+            match SledDB::open(&path.0) {
+                Err(err) if err.is::<sled::Error>() && attempts < 50 => {
+                    attempts += 1;
+                    std::thread::sleep(std::time::Duration::from_millis(20));
+                }
+                opened => return opened,
+            }
+        }
+    }
+
+    fn tx() -> TxObject {
+        TxObject::new(
+            VaneCallData::SubstrateCallData { amount: 10 },
+            MultiAddress::Id(AccountId32([1; 32])),
+            MultiAddress::Id(AccountId32([2; 32])),
+            BlockchainNetwork::Polkadot,
+        )
+    }
+
+    #[test]
+    fn mock_db_leases_each_entry_until_its_lease_expires() {
+        let mut db = MockDB::default();
+        db.push_simulation(b"first".to_vec()).unwrap();
+        db.push_simulation(b"second".to_vec()).unwrap();
+
+        assert_eq!(
+            db.lease_simulation(0, 1_000).unwrap(),
+            Some((1, b"first".to_vec()))
+        );
+        assert_eq!(
+            db.lease_simulation(0, 1_000).unwrap(),
+            Some((2, b"second".to_vec()))
+        );
+        assert_eq!(db.lease_simulation(999, 1_000).unwrap(), None);
+        // never acknowledged, delivered again once the lease expired
+        assert_eq!(
+            db.lease_simulation(1_000, 1_000).unwrap(),
+            Some((1, b"first".to_vec()))
+        );
+        assert_eq!(db.simulation_len().unwrap(), 2);
+    }
+
+    #[test]
+    fn mock_db_drops_acknowledged_entries() {
+        let mut db = MockDB::default();
+        db.push_simulation(b"first".to_vec()).unwrap();
+        let (id, _) = db.lease_simulation(0, 1_000).unwrap().unwrap();
+
+        assert!(db.ack_simulation(id).unwrap());
+        assert!(!db.ack_simulation(id).unwrap());
+        assert!(!db.is_simulation_queued(id).unwrap());
+        // acknowledged entries are not delivered again
+        assert_eq!(db.lease_simulation(5_000, 1_000).unwrap(), None);
+    }
+
+    #[test]
+    fn sled_db_keeps_pending_transactions_across_restarts() {
+        let path = TempPath::new();
+        let tx = tx();
+        let tx_key = TxKey::from(&tx);
+        {
+            let mut db = SledDB::open(&path.0).unwrap();
+            db.set_transaction(tx_key.clone(), tx.encode()).unwrap();
+            db.set_multi_ids(tx.sender_address.clone(), vec![tx_key.clone()])
+                .unwrap();
+            db.push_simulation(b"first".to_vec()).unwrap();
+            db.push_simulation(b"second".to_vec()).unwrap();
+            // leased to a consumer which never acknowledged before the restart
+            assert_eq!(db.lease_simulation(0, 1_000).unwrap().unwrap().1, b"first");
+        }
+
+        let mut db = reopen(&path).unwrap();
+        assert_eq!(
+            db.get_multi_ids(&tx.sender_address).unwrap(),
+            Some(vec![tx_key.clone()])
+        );
+        let stored = db.get_transaction(&tx_key).unwrap().unwrap();
+        assert_eq!(TxObject::decode(&mut &stored[..]).unwrap(), tx);
+        assert_eq!(db.simulation_len().unwrap(), 2);
+        // the lease survives the restart and the entry is delivered again once it expires
+        assert_eq!(
+            db.lease_simulation(500, 1_000).unwrap().unwrap().1,
+            b"second"
+        );
+        assert_eq!(db.lease_simulation(500, 1_000).unwrap(), None);
+        assert_eq!(
+            db.lease_simulation(1_000, 1_000).unwrap().unwrap().1,
+            b"first"
+        );
+    }
+
+    #[test]
+    fn sled_db_applies_a_batch_across_trees() {
+        let path = TempPath::new();
+        let tx = tx();
+        let tx_key = TxKey::from(&tx);
+        {
+            let mut db = SledDB::open(&path.0).unwrap();
+            db.push_simulation(b"first".to_vec()).unwrap();
+            db.apply(vec![
+                StorageWrite::SetTransaction(tx_key.clone(), tx.encode()),
+                StorageWrite::SetMultiIds(tx.sender_address.clone(), vec![tx_key.clone()]),
+                StorageWrite::SetMultiIds(tx.receiver_address.clone(), vec![tx_key.clone()]),
+                StorageWrite::SetConfirmation(tx_key.clone(), b"confirmed".to_vec()),
+                StorageWrite::PushSimulation(b"second".to_vec()),
+            ])
+            .unwrap();
+            db.push_simulation(b"third".to_vec()).unwrap();
+        }
+
+        let mut db = reopen(&path).unwrap();
+        assert!(db.get_transaction(&tx_key).unwrap().is_some());
+        for account in [&tx.sender_address, &tx.receiver_address] {
+            assert_eq!(
+                db.get_multi_ids(account).unwrap(),
+                Some(vec![tx_key.clone()])
+            );
+        }
+        assert_eq!(
+            db.get_confirmation(&tx_key).unwrap(),
+            Some(b"confirmed".to_vec())
+        );
+        // batched entries keep their place in the queue
+        for expected in [&b"first"[..], b"second", b"third"] {
+            assert_eq!(db.lease_simulation(0, 1_000).unwrap().unwrap().1, expected);
+        }
+    }
+
+    #[test]
+    fn sled_db_indexes_transactions_by_tx_id_and_expiry() {
+        let path = TempPath::new();
+        let (first, second) = (tx(), tx());
+        let (first_key, second_key) = (TxKey::from(&first), TxKey::from(&second));
+        {
+            let mut db = SledDB::open(&path.0).unwrap();
+            for (expires_at, tx_key, tx) in [(20, &first_key, &first), (10, &second_key, &second)] {
+                db.apply(vec![
+                    StorageWrite::SetTransaction(tx_key.clone(), tx.encode()),
+                    StorageWrite::IndexExpiry(expires_at, tx_key.clone()),
+                ])
+                .unwrap();
+            }
+        }
+
+        let mut db = reopen(&path).unwrap();
+        assert_eq!(
+            db.find_tx_key(&first.get_tx_id()).unwrap(),
+            Some(first_key.clone())
+        );
+        assert_eq!(
+            db.expiring_transactions(20).unwrap(),
+            vec![(10, second_key.clone()), (20, first_key.clone())]
+        );
+        assert_eq!(
+            db.expiring_transactions(19).unwrap(),
+            vec![(10, second_key.clone())]
+        );
+
+        db.remove_transaction(&first_key).unwrap();
+        db.apply(vec![StorageWrite::UnindexExpiry(10, second_key.clone())])
+            .unwrap();
+        assert_eq!(db.find_tx_key(&first.get_tx_id()).unwrap(), None);
+        assert_eq!(
+            db.find_tx_key(&second.get_tx_id()).unwrap(),
+            Some(second_key)
+        );
+        assert_eq!(
+            db.expiring_transactions(u64::MAX).unwrap(),
+            vec![(20, first_key)]
+        );
+    }
+
+    #[test]
+    fn sled_db_refuses_an_incompatible_layout() {
+        let path = TempPath::new();
+        {
+            let db = SledDB::open(&path.0).unwrap();
+            db.meta
+                .insert(STORAGE_VERSION_KEY, (STORAGE_VERSION + 1).encode())
+                .unwrap();
+        }
+        let err = reopen(&path).err().expect("incompatible layout opened");
+        assert!(err.to_string().contains("layout version"));
+    }
+
+    #[test]
+    fn sled_db_refuses_data_without_layout_version() {
+        let path = TempPath::new();
+        {
+            let db = sled::open(&path.0).unwrap();
+            db.open_tree("transactions")
+                .unwrap()
+                .insert(b"key", b"value")
+                .unwrap();
+            db.flush().unwrap();
+        }
+        let err = reopen(&path).err().expect("unversioned data opened");
+        assert!(err.to_string().contains("no layout version"));
+    }
+}
@@ -0,0 +1,279 @@
+use primitives::{BlockchainNetwork, ConfirmationPayload, REVERT_DOMAIN};
+use sp_core::keccak_256;
+
+// ============================================================================
+// EVM ( secp256k1 ) confirmation signatures
+
+/// EIP-712 domain name all Vane confirmations are signed under
+const EIP712_DOMAIN_NAME: &[u8] = b"Vane";
+const EIP712_DOMAIN_VERSION: &[u8] = b"1";
+
+const EIP712_DOMAIN_TYPE: &[u8] = b"EIP712Domain(string name,string version,uint256 chainId)";
+/// Members of the `ConfirmationPayload` typed struct, shared by confirmations and reverts
+const PAYLOAD_MEMBERS: &str = "(string txId,string multiId,string sender,string receiver,string network,uint256 amount,string role,uint64 expiresAt)";
+
+/// secp256k1 group order halved, signatures with a higher `s` are the malleated twin of a low `s` one
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// Chain id the EIP-712 domain is bound to, `None` for non EVM networks
+pub fn chain_id(network: &BlockchainNetwork) -> Option<u64> {
+    match network {
+        BlockchainNetwork::Ethereum => Some(1),
+        BlockchainNetwork::Moonbeam => Some(1284),
+        BlockchainNetwork::Optimism => Some(10),
+        BlockchainNetwork::Arbitrum => Some(42161),
+        _ => None,
+    }
+}
+
+/// Digest signed by `personal_sign` ( EIP-191 version 0x45 )
+pub fn personal_sign_hash(message: &[u8]) -> [u8; 32] {
+    let mut prefixed = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    prefixed.extend_from_slice(message);
+    keccak_256(&prefixed)
+}
+
+/// Big endian 32 bytes word of an unsigned integer
+fn word(value: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// EIP-712 type of the payload, `VaneRevert` for reverts and `VaneConfirmation` otherwise
+/// so a confirmation signature can never pass for a revert
+fn payload_type(payload: &ConfirmationPayload) -> String {
+    let name = if payload.domain == REVERT_DOMAIN {
+        "VaneRevert"
+    } else {
+        "VaneConfirmation"
+    };
+    format!("{name}{PAYLOAD_MEMBERS}")
+}
+
+/// Digest signed by `eth_signTypedData_v4` for the payload typed struct
+/// addresses are given as the `Display` rendering of the payload shows them
+pub fn typed_data_hash(payload: &ConfirmationPayload, chain_id: u64) -> [u8; 32] {
+    let domain_separator = keccak_256(
+        &[
+            keccak_256(EIP712_DOMAIN_TYPE),
+            keccak_256(EIP712_DOMAIN_NAME),
+            keccak_256(EIP712_DOMAIN_VERSION),
+            word(chain_id.into()),
+        ]
+        .concat(),
+    );
+    let [multi_id, sender, receiver] = payload.rendered_addresses();
+    let struct_hash = keccak_256(
+        &[
+            keccak_256(payload_type(payload).as_bytes()),
+            keccak_256(payload.tx_id.as_str().as_bytes()),
+            keccak_256(multi_id.as_bytes()),
+            keccak_256(sender.as_bytes()),
+            keccak_256(receiver.as_bytes()),
+            keccak_256(format!("{:?}", payload.network).as_bytes()),
+            word(payload.amount),
+            keccak_256(format!("{:?}", payload.role).as_bytes()),
+            word(payload.expires_at.into()),
+        ]
+        .concat(),
+    );
+
+    keccak_256(&[&b"\x19\x01"[..], &domain_separator, &struct_hash].concat())
+}
+
+/// Recover the 20 byte address which produced the 65 bytes `r || s || v` signature over `digest`
+/// `v` is accepted both as 0/1 and as 27/28 as returned by wallets, high `s` signatures are refused
+pub fn recover_address(digest: &[u8; 32], signature: &[u8]) -> anyhow::Result<[u8; 20]> {
+    let mut sig: [u8; 65] = signature
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid secp256k1 signature length {}", signature.len()))?;
+    if sig[64] >= 27 {
+        sig[64] -= 27;
+    }
+    if sig[64] > 1 {
+        return Err(anyhow::anyhow!("Invalid secp256k1 signature recovery id"));
+    }
+    // big endian byte order compares like the number
+    if sig[32..64] > SECP256K1_HALF_ORDER[..] {
+        return Err(anyhow::anyhow!("High s secp256k1 signature"));
+    }
+    let public = sp_io::crypto::secp256k1_ecdsa_recover(&sig, digest)
+        .map_err(|_| anyhow::anyhow!("Failed to recover secp256k1 public key"))?;
+
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&keccak_256(&public)[12..]);
+    Ok(address)
+}
+
+/// Whether `signature` is the `personal_sign` of `message` by `address`
+pub fn verify_personal_sign(
+    message: &[u8],
+    signature: &[u8],
+    address: &[u8; 20],
+) -> anyhow::Result<bool> {
+    Ok(recover_address(&personal_sign_hash(message), signature)? == *address)
+}
+
+/// Whether `signature` is the EIP-712 signature of the `payload` typed struct by `address`
+/// on the domain of the payload network
+pub fn verify_typed_data(
+    payload: &ConfirmationPayload,
+    signature: &[u8],
+    address: &[u8; 20],
+) -> anyhow::Result<bool> {
+    let network = &payload.network;
+    let chain_id = chain_id(network).ok_or(anyhow::anyhow!("{network:?} is not an EVM network"))?;
+    Ok(recover_address(&typed_data_hash(payload, chain_id), signature)? == *address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitives::{ConfirmationRole, VaneMultiAddress, CONFIRMATION_DOMAIN};
+
+    // Key 0x0123456789012345678901234567890123456789012345678901234567890123
+    const ADDRESS: &str = "0x14791697260e4c9a71f18484c9f997b308e59325";
+    // `personal_sign("Hello World")` as `r || s`, `v` is 27
+    const PERSONAL_SIGNATURE: &str = "0xc265132b334f1e6627c6445b632cd502b81e78732a8ae23ee2c96fd51726263077464561c32cb13c39c56e2178e2a72b0c163d29f8f37044281d8f3b10e0f690";
+    // `eth_signTypedData_v4` of the `payload()` receiver confirmation on chain 1 as `r || s`, `v` is 28
+    const TYPED_SIGNATURE: &str = "0x215ad468477a159e31b7f73309829481e99ab2b04e9b606c808b7ea806cf35d66d39a762d7881ab6619ed0689099b56b46b35ac85dfa7278d80882fadf1a6558";
+    // the same signature with `s` replaced by `n - s`, `v` is 27
+    const HIGH_S_TYPED_SIGNATURE: &str = "0x215ad468477a159e31b7f73309829481e99ab2b04e9b606c808b7ea806cf35d692c6589d2877e5499e612f976f664a9373fb821e514e2dc2e7c9db91f11bdbe9";
+    // `eth_signTypedData_v4` of the `payload()` sender revert on chain 1 as `r || s`, `v` is 28
+    const REVERT_SIGNATURE: &str = "0x215ad468477a159e31b7f73309829481e99ab2b04e9b606c808b7ea806cf35d658cc46ec6218fd88a900a766cedf9cec2e10b780db06c4159215041a40d15b1c";
+
+    fn hex(value: &str) -> Vec<u8> {
+        sp_core::bytes::from_hex(value).unwrap()
+    }
+
+    fn address() -> [u8; 20] {
+        hex(ADDRESS).try_into().unwrap()
+    }
+
+    fn with_v(signature: &str, v: u8) -> Vec<u8> {
+        let mut signature = hex(signature);
+        signature.push(v);
+        signature
+    }
+
+    fn payload(network: BlockchainNetwork) -> ConfirmationPayload {
+        ConfirmationPayload {
+            domain: CONFIRMATION_DOMAIN.to_vec(),
+            tx_id: serde_json::from_value(format!("0x{}", "11".repeat(32)).into()).unwrap(),
+            multi_id: VaneMultiAddress::Address32([0x33; 32]),
+            sender_address: VaneMultiAddress::Address20(address()),
+            receiver_address: VaneMultiAddress::Address20([0x44; 20]),
+            network,
+            amount: 1_000_000_000_000_000_000,
+            role: ConfirmationRole::Receiver,
+            expires_at: 1_700_000_000_000,
+        }
+    }
+
+    fn revert_payload() -> ConfirmationPayload {
+        ConfirmationPayload {
+            domain: REVERT_DOMAIN.to_vec(),
+            role: ConfirmationRole::Sender,
+            ..payload(BlockchainNetwork::Ethereum)
+        }
+    }
+
+    #[test]
+    fn personal_sign_hash_matches_eip191() {
+        assert_eq!(
+            personal_sign_hash(b"Hello World").to_vec(),
+            hex("0xa1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2")
+        );
+    }
+
+    #[test]
+    fn typed_data_hash_matches_eip712() {
+        assert_eq!(
+            typed_data_hash(&payload(BlockchainNetwork::Ethereum), 1).to_vec(),
+            hex("0x69db7cd48b0eadffc5dfc684f476b7e65fdf1ec6dce3904fcfd30a4cfda9ebed")
+        );
+        assert_eq!(
+            typed_data_hash(&revert_payload(), 1).to_vec(),
+            hex("0x29eb0bd899026761c72b80b2e4fce9c79d0fccf654e32a7fee4ce0b59ac717aa")
+        );
+    }
+
+    #[test]
+    fn recovers_personal_sign_signer_with_either_v_encoding() {
+        let digest = personal_sign_hash(b"Hello World");
+        for v in [27, 0] {
+            let signature = with_v(PERSONAL_SIGNATURE, v);
+            assert_eq!(recover_address(&digest, &signature).unwrap(), address());
+        }
+        // the other recovery id resolves to another key
+        for v in [28, 1] {
+            let signature = with_v(PERSONAL_SIGNATURE, v);
+            assert_ne!(recover_address(&digest, &signature).unwrap(), address());
+        }
+        assert!(recover_address(&digest, &with_v(PERSONAL_SIGNATURE, 29)).is_err());
+        assert!(recover_address(&digest, &hex(PERSONAL_SIGNATURE)).is_err());
+    }
+
+    #[test]
+    fn verifies_personal_sign_and_typed_data_signatures() {
+        let personal = with_v(PERSONAL_SIGNATURE, 27);
+        assert!(verify_personal_sign(b"Hello World", &personal, &address()).unwrap());
+
+        let payload = payload(BlockchainNetwork::Ethereum);
+        assert!(verify_typed_data(&payload, &with_v(TYPED_SIGNATURE, 28), &address()).unwrap());
+        assert!(verify_typed_data(&payload, &with_v(TYPED_SIGNATURE, 1), &address()).unwrap());
+        assert!(
+            verify_typed_data(&revert_payload(), &with_v(REVERT_SIGNATURE, 28), &address())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_high_s_signatures() {
+        let payload = payload(BlockchainNetwork::Ethereum);
+        let digest = typed_data_hash(&payload, 1);
+        // the malleated twin recovers the same key without the check
+        assert!(recover_address(&digest, &with_v(HIGH_S_TYPED_SIGNATURE, 27)).is_err());
+        assert!(
+            verify_typed_data(&payload, &with_v(HIGH_S_TYPED_SIGNATURE, 27), &address()).is_err()
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_address_chain_id_and_payload() {
+        let typed = with_v(TYPED_SIGNATURE, 28);
+        let payload = payload(BlockchainNetwork::Ethereum);
+
+        assert!(!verify_typed_data(&payload, &typed, &[0x11; 20]).unwrap());
+        // the EIP-712 domain binds the signature to chain 1
+        for network in [BlockchainNetwork::Optimism, BlockchainNetwork::Moonbeam] {
+            let payload = ConfirmationPayload {
+                network,
+                ..payload.clone()
+            };
+            assert!(!verify_typed_data(&payload, &typed, &address()).unwrap());
+        }
+        // every member is hashed
+        let payload_with_amount = ConfirmationPayload {
+            amount: 1,
+            ..payload.clone()
+        };
+        assert!(!verify_typed_data(&payload_with_amount, &typed, &address()).unwrap());
+        // a confirmation signature does not revert the transaction
+        let confirmation = ConfirmationPayload {
+            role: ConfirmationRole::Sender,
+            ..payload.clone()
+        };
+        let revert = with_v(REVERT_SIGNATURE, 28);
+        assert!(!verify_typed_data(&confirmation, &revert, &address()).unwrap());
+        let polkadot = ConfirmationPayload {
+            network: BlockchainNetwork::Polkadot,
+            ..payload
+        };
+        assert!(verify_typed_data(&polkadot, &typed, &address()).is_err());
+    }
+}
@@ -1,29 +1,32 @@
+use crate::notification::{NotificationHub, TxNotification};
+use crate::risk::AddressRiskAnalyzer;
 use crate::traits::*;
+use crate::verifier::{VerificationError, VerifierRegistry};
+use jsonrpsee::core::RpcResult;
 use jsonrpsee::core::{async_trait, SubscriptionResult};
-use jsonrpsee::core::{Error::Custom, RpcResult};
-use jsonrpsee::{PendingSubscriptionSink, SubscriptionMessage};
-use parity_scale_codec::alloc::sync::Once;
+use jsonrpsee::{PendingSubscriptionSink, SubscriptionMessage, SubscriptionSink};
 use parity_scale_codec::{Decode, Encode};
 use primitives::{
-    BlockchainNetwork, ConfirmationStatus, TxConfirmationObject, TxObject, TxSimulationObject,
-    VaneCallData, VaneMultiAddress,
+    BlockchainNetwork, ConfirmationPayload, ConfirmationRole, ConfirmationStatus,
+    KnownCounterparty, LifetimeStatus, ResponseEncoding, SimulationStatus, SubscriptionUpdate,
+    TxConfirmationObject, TxEvent, TxExecutionStatus, TxId, TxObject, TxSimulationDelivery,
+    TxSimulationObject, TxStateObject, TxSubmissionObject, VaneCallData, VaneError,
+    VaneMultiAddress,
 };
+use serde::Serialize;
 use serde_json::Value as JsonValue;
-use sp_core::ecdsa::{Public as ecdsaPublic, Signature as ECDSASignature};
-use sp_core::ed25519::{Public as ed25519Public, Signature as Ed25519Signature};
-use sp_core::sr25519::{Public as sr25519Public, Signature as Sr25519Signature};
-use sp_core::H256;
-use sp_runtime::traits::Verify;
+use sp_core::crypto::{AccountId32 as SpAccountId32, Ss58Codec};
+use sp_core::Bytes;
 use std::{
-    collections::{BTreeMap, HashMap, VecDeque},
+    collections::VecDeque,
     sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use subxt::utils::{AccountId32, MultiAddress, MultiSignature};
-use tokio::sync::Mutex;
-use tracing_subscriber;
+use subxt::utils::AccountId32;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tokio::sync::{Mutex, Notify};
 
 // Tracing setup
-static INIT: Once = Once::new();
 pub fn init_tracing() -> anyhow::Result<()> {
     // Add test tracing (from sp_tracing::init_for_tests()) but filtering for xcm logs only
     let vane_subscriber = tracing_subscriber::fmt()
@@ -40,134 +43,598 @@ pub fn init_tracing() -> anyhow::Result<()> {
 
 /// Types for easier code navigation
 pub type MultiId = VaneMultiAddress<AccountId32, ()>;
-/// A mock database storing each address to the transactions each having a key
-/// `address` ===> `multi_id`=====> `Vec<u8>`
-pub struct MockDB {
-    // ============================================================================
-    // DB_DATA
 
-    // Map of multi_id account to encoded transactions
-    pub transactions: BTreeMap<MultiId, Vec<u8>>,
-    // Map of account id per user ( sender | receiver ) to array of multi_id ( indicating pending transactions)
-    pub multi_ids: BTreeMap<VaneMultiAddress<AccountId32, ()>, Vec<MultiId>>,
-    // Map to store confirmation phase of transactions
-    // `multi-id` to `TxConfrimationObject`
-    pub confirmation: BTreeMap<MultiId, Vec<u8>>,
-    // Store ready to be simulated tx `TxSimulationObject` (queue)
-    pub simulation: VecDeque<Vec<u8>>,
-    // Record reverted transactions per sender
-    pub reverted_transactions: BTreeMap<MultiAddress<AccountId32, ()>, Vec<u8>>,
-
-    // ============================================================================
-    // METRICS
+/// Storage key of a single transaction
+/// `multi_id` is shared by every transaction between the same sender and receiver
+/// so the `tx_id` keeps repeated transactions between the same pair apart
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
+pub struct TxKey {
+    pub multi_id: MultiId,
+    pub tx_id: TxId,
+}
+
+impl TxKey {
+    pub fn new(multi_id: MultiId, tx_id: TxId) -> Self {
+        Self { multi_id, tx_id }
+    }
+}
+
+impl From<&TxObject> for TxKey {
+    fn from(tx: &TxObject) -> Self {
+        Self::new(tx.get_multi_id(), tx.get_tx_id())
+    }
+}
+
+/// Shared handle to the storage backend selected at startup
+pub type DbHandle = Arc<Mutex<Box<dyn TxStorage>>>;
+
+/// Time a delivered `TxSimulationObject` waits for acknowledgement before being delivered again
+pub const SIMULATION_LEASE: Duration = Duration::from_secs(30);
+/// Maximum unacknowledged deliveries per `receiveConfirmedTx` subscription
+pub const MAX_UNACKED_SIMULATIONS: usize = 16;
+/// Interval at which an idle feed looks for expired leases
+const LEASE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Interval at which transactions past their confirmation window are expired
+pub const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// Confirmations failing signature verification after which a transaction is locked
+pub const DEFAULT_MAX_FAILED_CONFIRMATIONS: u32 = 5;
+
+fn storage_error(err: anyhow::Error) -> jsonrpsee::core::Error {
+    VaneError::Storage(err.to_string()).into()
+}
+
+/// Decode a stored value, a value which does not decode is reported as a storage error
+fn decode<T: Decode>(encoded: &[u8]) -> RpcResult<T> {
+    T::decode(&mut &encoded[..])
+        .map_err(|err| VaneError::Storage(format!("Failed to decode stored value: {err}")).into())
+}
+
+/// Stored confirmation of the transaction, read with the storage lock already held
+fn read_confirmation(
+    db: &dyn TxStorage,
+    tx_key: &TxKey,
+) -> RpcResult<Option<TxConfirmationObject>> {
+    db.get_confirmation(tx_key)
+        .map_err(storage_error)?
+        .map(|confirmation_data| decode(&confirmation_data))
+        .transpose()
+}
+
+/// Remember the parties of a transaction confirmed by both of them, with the storage lock already held
+fn record_counterparty(
+    db: &mut dyn TxStorage,
+    tx: &TxConfirmationObject,
+    now: u64,
+) -> anyhow::Result<()> {
+    let sender = tx.get_sender_address();
+    let receiver = tx.get_receiver_address();
+    let network = tx.get_network();
+    let mut counterparties = db
+        .get_counterparties(&sender)?
+        .iter()
+        .map(|counterparty| KnownCounterparty::decode(&mut &counterparty[..]))
+        .collect::<Result<Vec<_>, _>>()?;
+    match counterparties
+        .iter_mut()
+        .find(|known| known.receiver == receiver && known.network == network)
+    {
+        Some(known) => {
+            known.confirmed_txs += 1;
+            known.last_confirmed = now;
+        }
+        None => counterparties.push(KnownCounterparty {
+            receiver,
+            network,
+            confirmed_txs: 1,
+            last_confirmed: now,
+        }),
+    }
+    db.set_counterparties(sender, counterparties.iter().map(Encode::encode).collect())
+}
+
+/// Only transactions the receiver did not confirm yet accept a receiver confirmation
+fn ensure_waiting_for_receiver(
+    tx_id: &TxId,
+    tx_confirmation: Option<&TxConfirmationObject>,
+) -> RpcResult<()> {
+    match tx_confirmation.map(TxConfirmationObject::get_confirmation_status) {
+        None | Some(ConfirmationStatus::WaitingForReceiver) => Ok(()),
+        Some(actual) => Err(VaneError::InvalidState {
+            tx_id: tx_id.clone(),
+            expected: ConfirmationStatus::WaitingForReceiver,
+            actual,
+        }
+        .into()),
+    }
+}
 
-    // Keep track of subscibed clients "id"
-    pub subscribed: Vec<JsonValue>,
+/// Address in the form the parties confirm and subscribe with
+/// `Raw` address text is decoded to the account it names, other addresses are kept as is
+fn canonical_address(address: MultiId, network: &BlockchainNetwork) -> MultiId {
+    let VaneMultiAddress::Raw(raw) = &address else {
+        return address;
+    };
+    if raw.len() == 20 || raw.len() == 32 {
+        return address;
+    }
+    let Ok(text) = std::str::from_utf8(raw) else {
+        return address;
+    };
+    let account = match VaneMultiAddress::parse(text, network) {
+        Ok(VaneMultiAddress::Address32(account)) if network.ss58_prefix().is_some() => account,
+        Ok(parsed) => return parsed,
+        // SS58 of another network still names the account, the risk warnings point out the prefix
+        Err(_) if network.ss58_prefix().is_some() && !network.uses_evm_accounts() => {
+            match SpAccountId32::from_ss58check_with_version(text) {
+                Ok((account, _)) => account.into(),
+                Err(_) => return address,
+            }
+        }
+        Err(_) => return address,
+    };
+    VaneMultiAddress::Id(AccountId32(account))
+}
+
+/// Unix time in millis
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time before unix epoch")
+        .as_millis() as u64
 }
 
 // TODO!
 // The field should be private
+#[derive(Clone)]
 pub struct TransactionHandler {
-    pub db: Arc<Mutex<MockDB>>,
+    pub db: DbHandle,
+    pub hub: NotificationHub,
+    // Wakes up `receiveConfirmedTx` feeds on new or acknowledged simulation entries
+    pub simulation_notify: Arc<Notify>,
+    // Signature scheme of every supported network for confirmations
+    pub verifiers: VerifierRegistry,
+    // Failed confirmations after which the transaction is locked
+    pub max_failed_confirmations: u32,
+    // Flags risky addresses on submission
+    pub risk: Arc<AddressRiskAnalyzer>,
 }
 
 impl TransactionHandler {
+    pub fn new(
+        db: Box<dyn TxStorage>,
+        max_failed_confirmations: u32,
+        risk: AddressRiskAnalyzer,
+    ) -> Self {
+        Self {
+            db: Arc::new(Mutex::new(db)),
+            hub: NotificationHub::default(),
+            simulation_notify: Arc::new(Notify::new()),
+            verifiers: VerifierRegistry::default(),
+            max_failed_confirmations,
+            risk: Arc::new(risk),
+        }
+    }
+
     // DB_DATA
 
-    pub async fn set_transaction_data(
-        &self,
-        address: VaneMultiAddress<AccountId32, ()>,
-        multi_id: MultiId,
-        data: TxObject,
-    ) {
+    /// Record the transaction and add it to the pending transactions of its sender and receiver
+    /// as a single storage batch
+    pub async fn set_transaction_data(&self, tx_key: TxKey, data: TxObject) -> RpcResult<()> {
         let mut db = self.db.lock().await;
 
-        let mut inner_db_multi_ids = Vec::<VaneMultiAddress<AccountId32, ()>>::new();
-
-        inner_db_multi_ids.push(multi_id.clone());
-
-        db.transactions.insert(multi_id, data.encode());
-        db.multi_ids.insert(address, inner_db_multi_ids);
-        tracing::info!("recorded tx to the memory db")
-    }
-
-    pub async fn set_confirmation_transaction_data(
-        &self,
-        multi_id: MultiId,
-        tx_confirmation: TxConfirmationObject,
-    ) {
-        let mut db = self.db.lock().await;
-        db.confirmation.insert(multi_id, tx_confirmation.encode());
-        tracing::info!("recorded confirmation tx data to the memory db")
+        let mut accounts = vec![data.sender_address.clone(), data.receiver_address.clone()];
+        accounts.dedup();
+        let mut writes = vec![
+            StorageWrite::SetTransaction(tx_key.clone(), data.encode()),
+            StorageWrite::IndexExpiry(data.expires_at(), tx_key.clone()),
+        ];
+        for account in accounts {
+            // append to the address pending transactions
+            let mut inner_db_multi_ids = db
+                .get_multi_ids(&account)
+                .map_err(storage_error)?
+                .unwrap_or_default();
+            if !inner_db_multi_ids.contains(&tx_key) {
+                inner_db_multi_ids.push(tx_key.clone());
+            }
+            writes.push(StorageWrite::SetMultiIds(account, inner_db_multi_ids));
+        }
+        db.apply(writes).map_err(storage_error)?;
+        tracing::info!("recorded tx to the db");
+        Ok(())
     }
 
     pub async fn get_confirmation_transaction_data(
         &self,
-        multi_id: MultiId,
-    ) -> Option<TxConfirmationObject> {
+        tx_key: TxKey,
+    ) -> RpcResult<Option<TxConfirmationObject>> {
         let db = self.db.lock().await;
-        if let Some(confirmation_data) = db.confirmation.get(&multi_id) {
-            let tx_confirmation_object: TxConfirmationObject =
-                Decode::decode(&mut &confirmation_data[..])
-                    .expect("Failed to decode tx confirmation object");
-            Some(tx_confirmation_object)
-        } else {
-            return None;
-        }
+        read_confirmation(&**db, &tx_key)
     }
 
     pub async fn get_pending_multi_ids(
         &self,
         account: VaneMultiAddress<AccountId32, ()>,
-    ) -> Option<Vec<MultiId>> {
+    ) -> RpcResult<Option<Vec<TxKey>>> {
         let db = self.db.lock().await;
-        if let Some(multi_ids) = db.multi_ids.get(&account) {
-            Some(multi_ids.to_owned())
-        } else {
-            None
-        }
+        db.get_multi_ids(&account).map_err(storage_error)
     }
 
-    pub async fn get_transaction(&self, multi_id: MultiId) -> Option<TxObject> {
+    pub async fn get_transaction_data(&self, tx_key: TxKey) -> RpcResult<Option<TxObject>> {
         let db = self.db.lock().await;
 
-        if let Some(transaction) = db.transactions.get(&multi_id) {
-            let decoded_tx_object: TxObject =
-                Decode::decode(&mut &transaction[..]).expect("Failed to decode tx object");
-            return Some(decoded_tx_object);
+        db.get_transaction(&tx_key)
+            .map_err(storage_error)?
+            .map(|transaction| decode(&transaction))
+            .transpose()
+    }
+
+    /// Stored transaction with the `tx_id` whatever the pair of addresses
+    pub async fn find_transaction(&self, tx_id: &TxId) -> RpcResult<Option<TxObject>> {
+        let db = self.db.lock().await;
+        let Some(tx_key) = db.find_tx_key(tx_id).map_err(storage_error)? else {
+            return Ok(None);
+        };
+        db.get_transaction(&tx_key)
+            .map_err(storage_error)?
+            .map(|tx| decode(&tx))
+            .transpose()
+    }
+
+    /// Lease the next deliverable simulation entry for `SIMULATION_LEASE`
+    pub async fn lease_simulate_tx(&self, now: u64) -> RpcResult<Option<TxSimulationDelivery>> {
+        let mut db = self.db.lock().await;
+        let lease = SIMULATION_LEASE.as_millis() as u64;
+        if let Some((delivery_id, tx)) = db.lease_simulation(now, lease).map_err(storage_error)? {
+            let tx_sim: TxSimulationObject = decode(&tx)?;
+            Ok(Some(TxSimulationDelivery {
+                delivery_id,
+                tx: tx_sim,
+            }))
         } else {
-            None
+            Ok(None)
         }
     }
 
-    pub async fn propagate_tx(&self, tx_simulate: TxSimulationObject) {
+    pub async fn ack_simulate_tx(&self, delivery_id: u64) -> RpcResult<bool> {
         let mut db = self.db.lock().await;
-        db.simulation.push_front(tx_simulate.encode());
-        tracing::info!("recorded simulation tx object to the memory db")
+        let acked = db.ack_simulation(delivery_id).map_err(storage_error)?;
+        self.simulation_notify.notify_waiters();
+        Ok(acked)
     }
 
-    pub async fn get_total_number_of_simulated_tx(&self) -> u32 {
+    pub async fn is_simulate_tx_queued(&self, delivery_id: u64) -> RpcResult<bool> {
         let db = self.db.lock().await;
-        db.simulation.len().try_into().unwrap()
+        db.is_simulation_queued(delivery_id).map_err(storage_error)
     }
 
-    pub async fn get_simulate_tx(&self) -> Option<TxSimulationObject> {
+    /// Drop every trace of the transaction from the confirmation phase
+    /// and keep it in the sender reverted records
+    /// transactions confirmed by both parties or already closed are left untouched, locked ones can still be reverted
+    pub async fn revert_transaction_data(&self, tx: TxObject) -> RpcResult<()> {
         let mut db = self.db.lock().await;
-        if let Some(tx) = db.simulation.pop_front() {
-            let tx_sim: TxSimulationObject =
-                Decode::decode(&mut &tx[..]).expect("Failed to decode tx simulation object");
-            Some(tx_sim)
+        let tx_key = TxKey::from(&tx);
+
+        // checked under the lock of the removal so the sender confirmation can not slip in between
+        let confirmation_status = match db.get_confirmation(&tx_key).map_err(storage_error)? {
+            Some(tx_confirmation) => {
+                decode::<TxConfirmationObject>(&tx_confirmation)?.get_confirmation_status()
+            }
+            None => ConfirmationStatus::WaitingForReceiver,
+        };
+        if !matches!(
+            confirmation_status,
+            ConfirmationStatus::WaitingForReceiver
+                | ConfirmationStatus::WaitingForSender
+                | ConfirmationStatus::Locked
+        ) {
+            return Err(VaneError::InvalidState {
+                tx_id: tx_key.tx_id,
+                expected: ConfirmationStatus::WaitingForSender,
+                actual: confirmation_status,
+            }
+            .into());
+        }
+
+        db.remove_transaction(&tx_key).map_err(storage_error)?;
+        db.remove_confirmation(&tx_key).map_err(storage_error)?;
+        db.apply(vec![StorageWrite::UnindexExpiry(
+            tx.expires_at(),
+            tx_key.clone(),
+        )])
+        .map_err(storage_error)?;
+        for account in [tx.sender_address.clone(), tx.receiver_address.clone()] {
+            if let Some(mut multi_ids) = db.get_multi_ids(&account).map_err(storage_error)? {
+                multi_ids.retain(|pending| pending != &tx_key);
+                db.set_multi_ids(account, multi_ids)
+                    .map_err(storage_error)?;
+            }
+        }
+        let tx_id = tx.get_tx_id();
+        db.remove_simulation(&|tx_simulation| {
+            TxSimulationObject::decode(&mut &tx_simulation[..])
+                .map(|tx_simulation| tx_simulation.get_tx_id() == tx_id)
+                .unwrap_or(false)
+        })
+        .map_err(storage_error)?;
+        db.record_reverted(tx.sender_address.clone(), tx.encode())
+            .map_err(storage_error)?;
+        tracing::info!("recorded reverted tx to the db");
+        Ok(())
+    }
+
+    pub async fn get_reverted_transactions(
+        &self,
+        sender: VaneMultiAddress<AccountId32, ()>,
+    ) -> RpcResult<Vec<TxObject>> {
+        let db = self.db.lock().await;
+        let reverted = db.get_reverted(&sender).map_err(storage_error)?;
+        reverted.iter().map(|tx| decode(tx)).collect()
+    }
+
+    /// Assemble the lifecycle view of a pending transaction from every storage it lives in
+    pub async fn get_transaction_state(&self, tx: TxObject) -> RpcResult<TxStateObject> {
+        let tx_id = tx.get_tx_id();
+        let (queued, execution_status) = {
+            let db = self.db.lock().await;
+            let queued = db
+                .contains_simulation(&|tx_simulation| {
+                    TxSimulationObject::decode(&mut &tx_simulation[..])
+                        .map(|tx_simulation| tx_simulation.get_tx_id() == tx_id)
+                        .unwrap_or(false)
+                })
+                .map_err(storage_error)?;
+            let execution_status = db
+                .get_execution_status(&TxKey::from(&tx))
+                .map_err(storage_error)?
+                .map(|status| decode::<TxExecutionStatus>(&status))
+                .transpose()?;
+            (queued, execution_status)
+        };
+
+        let (confirmation_status, receiver_signed, sender_signed) = match self
+            .get_confirmation_transaction_data(TxKey::from(&tx))
+            .await?
+        {
+            Some(tx_confirmation) => (
+                tx_confirmation.get_confirmation_status(),
+                tx_confirmation.get_receiver_sig().is_some(),
+                tx_confirmation.get_sender_sig().is_some(),
+            ),
+            None => (ConfirmationStatus::WaitingForReceiver, false, false),
+        };
+
+        let simulation_status = if queued {
+            SimulationStatus::Queued
+        } else if confirmation_status == ConfirmationStatus::Ready {
+            SimulationStatus::Propagated
         } else {
-            None
+            SimulationStatus::NotReady
+        };
+
+        Ok(TxStateObject {
+            tx,
+            confirmation_status,
+            receiver_signed,
+            sender_signed,
+            simulation_status,
+            execution_status,
+        })
+    }
+
+    // LIFETIME
+
+    /// Move every transaction whose confirmation window passed to the terminal expired state
+    /// and let both parties know, transactions confirmed by both parties are left untouched
+    /// locked transactions expire as well so a lock never outlives the confirmation window
+    pub async fn expire_transactions(&self, now: u64) -> RpcResult<usize> {
+        // a single lock for the whole sweep so no confirmation lands between the check and the write
+        // only the transactions due are read, through the expiry index
+        let mut db = self.db.lock().await;
+        let due = db.expiring_transactions(now).map_err(storage_error)?;
+
+        let mut expired = 0;
+        for (expires_at, tx_key) in due {
+            let unindex = StorageWrite::UnindexExpiry(expires_at, tx_key.clone());
+            let Some(tx) = db.get_transaction(&tx_key).map_err(storage_error)? else {
+                db.apply(vec![unindex]).map_err(storage_error)?;
+                continue;
+            };
+            let mut tx: TxObject = decode(&tx)?;
+            let mut tx_confirmation =
+                read_confirmation(&**db, &tx_key)?.unwrap_or_else(|| tx.clone().into());
+            // confirmed transactions keep their final state
+            if tx.lifetime_status == LifetimeStatus::Invalid
+                || tx_confirmation.get_confirmation_status() == ConfirmationStatus::Ready
+            {
+                db.apply(vec![unindex]).map_err(storage_error)?;
+                continue;
+            }
+
+            tx.lifetime_status = LifetimeStatus::Invalid;
+            tx_confirmation.update_confirmation_status(ConfirmationStatus::Expired);
+            db.apply(vec![
+                StorageWrite::SetTransaction(tx_key.clone(), tx.encode()),
+                StorageWrite::SetConfirmation(tx_key.clone(), tx_confirmation.encode()),
+                unindex,
+            ])
+            .map_err(storage_error)?;
+            self.hub.publish(
+                vec![tx.sender_address.clone(), tx.receiver_address.clone()],
+                TxEvent::Expired {
+                    tx_id: tx_key.tx_id,
+                    multi_id: tx_key.multi_id,
+                },
+            );
+            expired += 1;
+        }
+        Ok(expired)
+    }
+
+    /// Background task expiring stale transactions every `EXPIRY_CHECK_INTERVAL`
+    pub async fn run_expiry_reaper(self) {
+        let mut interval = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match self.expire_transactions(now_millis()).await {
+                Ok(0) => {}
+                Ok(expired) => tracing::info!("expired {expired} stale transactions"),
+                Err(err) => tracing::error!("failed to expire stale transactions: {err}"),
+            }
+        }
+    }
+
+    // CONFIRMATION ATTEMPTS
+
+    pub async fn ensure_not_locked(&self, tx_key: &TxKey) -> RpcResult<()> {
+        let failed = {
+            let db = self.db.lock().await;
+            db.get_failed_confirmations(tx_key).map_err(storage_error)?
+        };
+        if failed >= self.max_failed_confirmations {
+            return Err(VaneError::TxLocked {
+                tx_id: tx_key.tx_id.clone(),
+            }
+            .into());
         }
+        Ok(())
+    }
+
+    /// Check the `role` confirmation `signature` of `address` over the transaction `ConfirmationPayload`
+    /// every signature failing the check is counted and the transaction is locked once `max_failed_confirmations` is reached
+    /// an address without a public key of the scheme is refused without counting, no signature can ever match it
+    pub async fn verify_confirmation(
+        &self,
+        tx: &TxObject,
+        role: ConfirmationRole,
+        address: &MultiId,
+        signature: &[u8],
+    ) -> RpcResult<()> {
+        let payload = ConfirmationPayload::new(tx, role);
+        let err = match self.verifiers.verify_payload(address, &payload, signature) {
+            Ok(()) => return Ok(()),
+            Err(VerificationError::UnsupportedNetwork(network)) => {
+                return Err(VaneError::UnsupportedNetwork(network).into())
+            }
+            Err(err @ VerificationError::InvalidAddress(_)) => {
+                return Err(VaneError::SignatureVerification {
+                    tx_id: tx.get_tx_id(),
+                    reason: err.to_string(),
+                }
+                .into())
+            }
+            Err(err) => err,
+        };
+
+        let tx_key = TxKey::from(tx);
+        let failed = {
+            let mut db = self.db.lock().await;
+            db.record_failed_confirmation(&tx_key)
+                .map_err(storage_error)?
+        };
+        tracing::warn!("{role:?} confirmation failed {failed} times: {err}");
+        if failed >= self.max_failed_confirmations {
+            self.lock_transaction(tx).await?;
+        }
+        Err(VaneError::SignatureVerification {
+            tx_id: tx_key.tx_id,
+            reason: err.to_string(),
+        }
+        .into())
+    }
+
+    /// Stop accepting confirmations for the transaction and let both parties know
+    /// transactions which left the confirmation phase meanwhile keep their state
+    async fn lock_transaction(&self, tx: &TxObject) -> RpcResult<()> {
+        let tx_key = TxKey::from(tx);
+        {
+            let mut db = self.db.lock().await;
+            let mut tx_confirmation =
+                read_confirmation(&**db, &tx_key)?.unwrap_or_else(|| tx.clone().into());
+            if !matches!(
+                tx_confirmation.get_confirmation_status(),
+                ConfirmationStatus::WaitingForReceiver | ConfirmationStatus::WaitingForSender
+            ) {
+                return Ok(());
+            }
+            tx_confirmation.update_confirmation_status(ConfirmationStatus::Locked);
+            db.set_confirmation(tx_key.clone(), tx_confirmation.encode())
+                .map_err(storage_error)?;
+        }
+        self.hub.publish(
+            vec![tx.sender_address.clone(), tx.receiver_address.clone()],
+            TxEvent::Locked {
+                tx_id: tx_key.tx_id,
+                multi_id: tx_key.multi_id,
+            },
+        );
+        tracing::warn!("transaction locked after too many failed confirmations");
+        Ok(())
+    }
+
+    // COUNTERPARTIES
+
+    pub async fn get_known_counterparties(
+        &self,
+        sender: &MultiId,
+    ) -> RpcResult<Vec<KnownCounterparty>> {
+        let db = self.db.lock().await;
+        let counterparties = db.get_counterparties(sender).map_err(storage_error)?;
+        counterparties
+            .iter()
+            .map(|counterparty| decode(counterparty))
+            .collect()
+    }
+
+    /// Whether `sender` already completed a transaction to `receiver` on `network`
+    pub async fn is_known_counterparty(
+        &self,
+        sender: &MultiId,
+        receiver: &MultiId,
+        network: &BlockchainNetwork,
+    ) -> RpcResult<bool> {
+        Ok(self
+            .get_known_counterparties(sender)
+            .await?
+            .iter()
+            .any(|known| &known.receiver == receiver && &known.network == network))
+    }
+
+    // SUBSCRIPTIONS
+
+    /// Push every event concerning `address` to the subscription for as long as it lives
+    /// `T` is the snapshot item of the subscription, see `SubscriptionUpdate`
+    pub async fn forward_events<T: Serialize>(
+        &self,
+        sink: &SubscriptionSink,
+        mut events: Receiver<TxNotification>,
+        address: &MultiId,
+    ) -> SubscriptionResult {
+        loop {
+            tokio::select! {
+                _ = sink.closed() => break,
+                notification = events.recv() => match notification {
+                    Ok(notification) => {
+                        if notification.concerns(address) {
+                            let update = SubscriptionUpdate::<T>::Event(Box::new(notification.event));
+                            sink.send(SubscriptionMessage::from_json(&update)?).await?;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("subscription lagging behind, skipped {skipped} events");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }
+        Ok(())
     }
 
     // METRICS
 
-    pub async fn record_subscriber(&self, id: JsonValue) {
+    pub async fn record_subscriber(&self, id: JsonValue) -> RpcResult<()> {
         let mut db = self.db.lock().await;
-        db.subscribed.push(id);
-        db.subscribed.dedup();
+        db.record_subscriber(id).map_err(storage_error)
     }
 }
 
@@ -175,38 +642,97 @@ impl TransactionHandler {
 impl TransactionServer for TransactionHandler {
     /// construct tx object and generate the multi id
     /// record the multi_id and set storage for sender and receiver
-    /// of the tx data with multi_id being the key
+    /// of the tx data with multi_id and tx_id being the key
     async fn submit_transaction(
         &self,
         call_data: VaneCallData,
         sender: VaneMultiAddress<AccountId32, ()>,
         receiver: VaneMultiAddress<AccountId32, ()>,
-    ) -> RpcResult<()> {
+        network: BlockchainNetwork,
+    ) -> RpcResult<TxSubmissionObject> {
+        if !call_data.matches_network(&network) {
+            return Err(VaneError::CallNetworkMismatch {
+                family: call_data.family(),
+                network,
+            }
+            .into());
+        }
+        // flag addresses likely to lose the funds on the network for both parties to review
+        let warnings = self.risk.analyze(&sender, &receiver, &network);
+        if !warnings.is_empty() {
+            tracing::warn!("submitted transaction with risky addresses: {warnings:?}");
+        }
+        // verifiers take the account bytes, not the address text
+        let sender = canonical_address(sender, &network);
+        let receiver = canonical_address(receiver, &network);
         // construct transaction object
-        let tx_object = TxObject::new(
+        let mut tx_object = TxObject::new(
             call_data,
             sender.clone().into(),
             receiver.clone().into(),
-            primitives::BlockchainNetwork::Polkadot,
+            network,
         );
+        tx_object.warnings = warnings.clone();
         tracing::info!("submitting transaction and preparing for confirmation phase");
         // record the tx object to the db
-        let multi_id = tx_object.get_multi_id();
-        // record for sender
-        self.set_transaction_data(sender.into(), multi_id.clone(), tx_object.clone())
-            .await;
-        // record for receiver
-        self.set_transaction_data(receiver.into(), multi_id, tx_object)
-            .await;
-        Ok(())
+        let tx_key = TxKey::from(&tx_object);
+        // record for sender and receiver
+        self.set_transaction_data(tx_key.clone(), tx_object.clone())
+            .await?;
+        self.hub
+            .publish(vec![sender, receiver], TxEvent::Submitted(tx_object));
+        Ok(TxSubmissionObject {
+            tx_id: tx_key.tx_id,
+            multi_id: tx_key.multi_id,
+            warnings,
+        })
     }
 
     async fn get_transaction(
         &self,
-        _sender: VaneMultiAddress<AccountId32, ()>,
-        _tx_id: Option<Vec<u8>>,
-    ) -> RpcResult<Vec<u8>> {
-        todo!()
+        sender: VaneMultiAddress<AccountId32, ()>,
+        tx_id: Option<TxId>,
+        encoding: Option<ResponseEncoding>,
+    ) -> RpcResult<JsonValue> {
+        let is_requested = |tx: &TxObject| -> bool {
+            tx.sender_address == sender
+                && tx_id.as_ref().is_none_or(|tx_id| &tx.get_tx_id() == tx_id)
+        };
+
+        let mut tx_states = Vec::<TxStateObject>::new();
+        let tx_keys = self
+            .get_pending_multi_ids(sender.clone())
+            .await?
+            .unwrap_or_default();
+        for tx_key in tx_keys {
+            if let Some(tx) = self.get_transaction_data(tx_key).await? {
+                if is_requested(&tx) {
+                    tx_states.push(self.get_transaction_state(tx).await?);
+                }
+            }
+        }
+        for tx in self.get_reverted_transactions(sender.clone()).await? {
+            if is_requested(&tx) {
+                tx_states.push(TxStateObject {
+                    tx,
+                    confirmation_status: ConfirmationStatus::Reverted,
+                    receiver_signed: false,
+                    sender_signed: false,
+                    simulation_status: SimulationStatus::NotReady,
+                    execution_status: None,
+                });
+            }
+        }
+
+        if let Some(tx_id) = tx_id.filter(|_| tx_states.is_empty()) {
+            return Err(VaneError::TxNotFound { tx_id }.into());
+        }
+
+        let response = match encoding.unwrap_or_default() {
+            ResponseEncoding::Scale => serde_json::to_value(Bytes(tx_states.encode())),
+            ResponseEncoding::Json => serde_json::to_value(&tx_states),
+        };
+        response.map_err(|err| VaneError::Encoding(err.to_string()).into())
     }
 
     async fn subscribe_tx_confirmation(
@@ -217,27 +743,27 @@ impl TransactionServer for TransactionHandler {
         let sink = pending.accept().await?;
         let sub_id: JsonValue = sink.subscription_id().into();
         // record metrics
-        self.record_subscriber(sub_id).await;
+        self.record_subscriber(sub_id).await?;
+        // listen before taking the snapshot so no event is missed in between
+        let events = self.hub.subscribe();
         // send all the multi_id pending
-        let multi_ids = self.get_pending_multi_ids(address.into()).await;
+        let multi_ids = self.get_pending_multi_ids(address.clone()).await?;
 
         let mut txs_vec = Vec::<TxObject>::new();
-        if let Some(multi_ids) = multi_ids {
-            for multi_id in multi_ids.clone() {
-                let encoded_txs = self.get_transaction(multi_id).await;
-                if let Some(tx) = encoded_txs {
-                    txs_vec.push(tx)
-                }
+        for tx_key in multi_ids.unwrap_or_default() {
+            let encoded_txs = self.get_transaction_data(tx_key).await?;
+            if let Some(tx) = encoded_txs {
+                txs_vec.push(tx)
             }
-
-            sink.send(SubscriptionMessage::from_json(&txs_vec)?).await?;
-        } else {
-            let empty_result = Vec::<Vec<u8>>::new();
-            sink.send(SubscriptionMessage::from_json(&empty_result)?)
-                .await?;
         }
+        sink.send(SubscriptionMessage::from_json(
+            &SubscriptionUpdate::Snapshot(txs_vec),
+        )?)
+        .await?;
         tracing::info!("subcribed to tx confirmation receiver");
-        Ok(())
+        // keep pushing the updates of the receiver transactions
+        self.forward_events::<TxObject>(&sink, events, &address)
+            .await
     }
 
     // Subscribe for sender to listen to confirmed tx from the receiver
@@ -249,130 +775,1329 @@ impl TransactionServer for TransactionHandler {
         let sink = pending.accept().await?;
         let sub_id: JsonValue = sink.subscription_id().into();
         // record metrics
-        self.record_subscriber(sub_id).await;
+        self.record_subscriber(sub_id).await?;
+        // listen before taking the snapshot so no event is missed in between
+        let events = self.hub.subscribe();
 
-        let multi_ids = self.get_pending_multi_ids(address.into()).await;
+        let multi_ids = self.get_pending_multi_ids(address.clone()).await?;
 
         let mut txs_vec = Vec::<TxConfirmationObject>::new();
-        if let Some(multi_ids) = multi_ids {
-            for multi_id in multi_ids.clone() {
-                let encoded_txs = self.get_confirmation_transaction_data(multi_id).await;
-                if let Some(tx) = encoded_txs {
-                    txs_vec.push(tx)
-                }
+        for tx_key in multi_ids.unwrap_or_default() {
+            let encoded_txs = self.get_confirmation_transaction_data(tx_key).await?;
+            if let Some(tx) = encoded_txs {
+                txs_vec.push(tx)
             }
-
-            sink.send(SubscriptionMessage::from_json(&txs_vec)?).await?;
-        } else {
-            let empty_result = Vec::<Vec<u8>>::new();
-            sink.send(SubscriptionMessage::from_json(&empty_result)?)
-                .await?;
         }
+        sink.send(SubscriptionMessage::from_json(
+            &SubscriptionUpdate::Snapshot(txs_vec),
+        )?)
+        .await?;
         tracing::info!("subcribed to tx confirmation sender");
-        Ok(())
+        self.forward_events::<TxConfirmationObject>(&sink, events, &address)
+            .await
     }
 
     async fn receiver_confirmation(
         &self,
         address: VaneMultiAddress<AccountId32, ()>,
         multi_id: VaneMultiAddress<AccountId32, ()>,
+        tx_id: TxId,
         signature: Vec<u8>,
         network: BlockchainNetwork,
     ) -> RpcResult<()> {
-        // verify the signature and the address
-        match network {
-            BlockchainNetwork::Kusama | BlockchainNetwork::Polkadot => {
-                let tx = self
-                    .get_transaction(multi_id.clone().into())
-                    .await
-                    .ok_or(Custom("Transaction Not Found".to_string()))?;
-                // record the confirmation
-
-                let msg = tx.clone().call;
-                let sig = Sr25519Signature::from_slice(&signature)
-                    .ok_or(Custom("Failed to convert signature sr25519".to_string()))?;
-
-                let account_bytes: [u8; 32] = address
-                    .encode()
-                    .try_into()
-                    .expect("Failed to covert address to bytes");
-                let public_account = sr25519Public::from_h256(H256::from(account_bytes));
-                if sig.verify(&msg.encode()[..], &public_account) {
-                    let mut tx_confirmation_object: TxConfirmationObject = tx.into();
-                    // update the confirmation status
-                    tx_confirmation_object.update_confirmation_status(
-                        primitives::ConfirmationStatus::WaitingForSender,
-                    );
-                    tx_confirmation_object.set_receiver_sig(signature);
-                    // store the tx confirmation object
-                    self.set_confirmation_transaction_data(multi_id.into(), tx_confirmation_object)
-                        .await
-                };
-                tracing::info!("receiver confirmed");
-                Ok(())
-            }
-            _ => Err(Custom("Blockchain network not supported".to_string())),
+        let tx_key = TxKey::new(multi_id, tx_id.clone());
+        let tx = self
+            .get_transaction_data(tx_key.clone())
+            .await?
+            .ok_or(VaneError::TxNotFound {
+                tx_id: tx_id.clone(),
+            })?;
+        if tx.receiver_address != address {
+            return Err(VaneError::NotReceiver { tx_id }.into());
+        }
+        self.ensure_not_locked(&tx_key).await?;
+        if tx.is_expired(now_millis()) {
+            return Err(VaneError::TxExpired { tx_id }.into());
         }
+        if tx.network != network {
+            return Err(VaneError::NetworkMismatch {
+                tx_id,
+                expected: tx.network,
+                actual: network,
+            }
+            .into());
+        }
+        // the receiver confirms once, a replayed confirmation must not move the transaction back
+        let stored = self
+            .get_confirmation_transaction_data(tx_key.clone())
+            .await?;
+        ensure_waiting_for_receiver(&tx_id, stored.as_ref())?;
+        // verify the signature and the address
+        self.verify_confirmation(&tx, ConfirmationRole::Receiver, &address, &signature)
+            .await?;
+
+        // record the confirmation
+        let addresses = vec![tx.sender_address.clone(), tx.receiver_address.clone()];
+        let trusted_receiver = self
+            .is_known_counterparty(&tx.sender_address, &tx.receiver_address, &tx.network)
+            .await?;
+        let tx_confirmation_object = {
+            let mut db = self.db.lock().await;
+            // checked again under the lock of the write, see `sender_confirmation`
+            ensure_waiting_for_receiver(&tx_id, read_confirmation(&**db, &tx_key)?.as_ref())?;
+            let mut tx_confirmation_object: TxConfirmationObject = tx.into();
+            // let the sender wallet show whether the receiver was already paid
+            tx_confirmation_object.set_trusted_receiver(trusted_receiver);
+            // update the confirmation status
+            tx_confirmation_object
+                .update_confirmation_status(primitives::ConfirmationStatus::WaitingForSender);
+            tx_confirmation_object.set_receiver_sig(signature);
+            // store the tx confirmation object
+            db.set_confirmation(tx_key, tx_confirmation_object.encode())
+                .map_err(storage_error)?;
+            tx_confirmation_object
+        };
+        self.hub.publish(
+            addresses,
+            TxEvent::ReceiverConfirmed(tx_confirmation_object),
+        );
+        tracing::info!("receiver confirmed");
+        Ok(())
     }
 
     async fn sender_confirmation(
         &self,
         address: VaneMultiAddress<AccountId32, ()>,
         multi_id: VaneMultiAddress<AccountId32, ()>,
+        tx_id: TxId,
         signature: Vec<u8>,
         network: BlockchainNetwork,
     ) -> RpcResult<()> {
-        match network {
-            BlockchainNetwork::Kusama | BlockchainNetwork::Polkadot => {
-                let mut tx = self
-                    .get_confirmation_transaction_data(multi_id.clone().into())
-                    .await
-                    .ok_or(Custom("Confirmation data unavailable".to_string()))?;
-                // check if the if the receiver has confirmed
-                if tx.get_confirmation_status() != ConfirmationStatus::WaitingForSender {
-                    return Err(Custom("Wait for receiver to confirm".to_string()));
-                }
-                // verify the signature and the address
-                let msg = tx.clone().call;
-                let sig = Sr25519Signature::from_slice(&signature)
-                    .expect("Failed to convert signature sr25519");
-
-                let account_bytes: [u8; 32] = address
-                    .encode()
-                    .try_into()
-                    .expect("Failed to covert address to bytes");
-                let public_account = sr25519Public::from_h256(H256::from(account_bytes));
-                if sig.verify(&msg.encode()[..], &public_account) {
-                    tx.update_confirmation_status(ConfirmationStatus::Ready);
-                    let tx_simulation_object: TxSimulationObject = tx.into();
-                    // store to the ready to be simulated tx storage
-                    self.propagate_tx(tx_simulation_object).await;
-                };
-                tracing::info!("sender confirmed");
-                Ok(())
+        let tx_key = TxKey::new(multi_id, tx_id.clone());
+        let tx = self
+            .get_confirmation_transaction_data(tx_key.clone())
+            .await?
+            .ok_or(VaneError::ConfirmationUnavailable {
+                tx_id: tx_id.clone(),
+            })?;
+        self.ensure_not_locked(&tx_key).await?;
+        if tx.is_expired(now_millis()) {
+            return Err(VaneError::TxExpired { tx_id }.into());
+        }
+        // check if the if the receiver has confirmed
+        if tx.get_confirmation_status() != ConfirmationStatus::WaitingForSender {
+            return Err(VaneError::InvalidState {
+                tx_id,
+                expected: ConfirmationStatus::WaitingForSender,
+                actual: tx.get_confirmation_status(),
+            }
+            .into());
+        }
+        let tx_object =
+            self.get_transaction_data(tx_key.clone())
+                .await?
+                .ok_or(VaneError::TxNotFound {
+                    tx_id: tx_id.clone(),
+                })?;
+        if tx_object.sender_address != address {
+            return Err(VaneError::NotSender { tx_id }.into());
+        }
+        if tx_object.network != network {
+            return Err(VaneError::NetworkMismatch {
+                tx_id,
+                expected: tx_object.network,
+                actual: network,
             }
-            _ => Err(Custom("Blockchain network not supported".to_string())),
+            .into());
         }
+        // verify the signature and the address
+        self.verify_confirmation(&tx_object, ConfirmationRole::Sender, &address, &signature)
+            .await?;
+
+        let tx = {
+            let mut db = self.db.lock().await;
+            // checked again under the lock of the writes, another confirmation or the expiry reaper
+            // may have moved the transaction while the signature was verified
+            let mut tx =
+                read_confirmation(&**db, &tx_key)?.ok_or(VaneError::ConfirmationUnavailable {
+                    tx_id: tx_id.clone(),
+                })?;
+            if tx.get_confirmation_status() != ConfirmationStatus::WaitingForSender {
+                return Err(VaneError::InvalidState {
+                    tx_id,
+                    expected: ConfirmationStatus::WaitingForSender,
+                    actual: tx.get_confirmation_status(),
+                }
+                .into());
+            }
+            if tx.is_expired(now_millis()) {
+                return Err(VaneError::TxExpired { tx_id }.into());
+            }
+            tx.update_confirmation_status(ConfirmationStatus::Ready);
+            tx.set_sender_sig(signature);
+            let tx_simulation_object: TxSimulationObject = tx.clone().into();
+            // keep the confirmed state for lookups and store to the ready to be simulated tx storage
+            // together, a confirmed transaction missing from the queue would never be delivered
+            db.apply(vec![
+                StorageWrite::SetConfirmation(tx_key.clone(), tx.encode()),
+                StorageWrite::PushSimulation(tx_simulation_object.encode()),
+                StorageWrite::UnindexExpiry(tx.expires_at(), tx_key),
+            ])
+            .map_err(storage_error)?;
+            // both signatures are valid, the receiver is trusted for the next transactions
+            // the confirmation is already stored so a failure only costs the trust hint
+            if let Err(err) = record_counterparty(&mut **db, &tx, now_millis()) {
+                tracing::error!("failed to record the counterparty of the confirmed tx: {err}");
+            }
+            tx
+        };
+        self.simulation_notify.notify_waiters();
+        let addresses = vec![tx.get_sender_address(), tx.get_receiver_address()];
+        self.hub.publish(addresses, TxEvent::SenderConfirmed(tx));
+        tracing::info!("sender confirmed");
+        Ok(())
     }
 
     async fn sender_revert_transaction(
         &self,
         address: VaneMultiAddress<AccountId32, ()>,
         multi_id: VaneMultiAddress<AccountId32, ()>,
+        tx_id: TxId,
+        signature: Vec<u8>,
         network: BlockchainNetwork,
     ) -> RpcResult<()> {
-        todo!()
+        let tx_key = TxKey::new(multi_id, tx_id.clone());
+        let tx = self
+            .get_transaction_data(tx_key.clone())
+            .await?
+            .ok_or(VaneError::TxNotFound {
+                tx_id: tx_id.clone(),
+            })?;
+        // only the sender holding the pending transaction can stop it
+        let sender_multi_ids = self
+            .get_pending_multi_ids(address.clone())
+            .await?
+            .unwrap_or_default();
+        if tx.sender_address != address || !sender_multi_ids.contains(&tx_key) {
+            return Err(VaneError::NotSender { tx_id }.into());
+        }
+        if tx.network != network {
+            return Err(VaneError::NetworkMismatch {
+                tx_id,
+                expected: tx.network,
+                actual: network,
+            }
+            .into());
+        }
+        if tx.is_expired(now_millis()) {
+            return Err(VaneError::TxExpired { tx_id }.into());
+        }
+        // the sender proves control of the address by signing the revert payload
+        let payload = ConfirmationPayload::revert(&tx);
+        match self
+            .verifiers
+            .verify_payload(&address, &payload, &signature)
+        {
+            Ok(()) => {}
+            Err(VerificationError::UnsupportedNetwork(network)) => {
+                return Err(VaneError::UnsupportedNetwork(network).into())
+            }
+            Err(err) => {
+                return Err(VaneError::SignatureVerification {
+                    tx_id,
+                    reason: err.to_string(),
+                }
+                .into())
+            }
+        }
+
+        self.revert_transaction_data(tx.clone()).await?;
+        // let both parties know the transaction is withdrawn
+        self.hub.publish(
+            vec![tx.sender_address.clone(), tx.receiver_address.clone()],
+            TxEvent::Reverted {
+                tx_id: tx_key.tx_id,
+                multi_id: tx_key.multi_id,
+            },
+        );
+        tracing::info!("sender reverted transaction");
+        Ok(())
+    }
+
+    async fn known_counterparties(
+        &self,
+        sender: VaneMultiAddress<AccountId32, ()>,
+        network: Option<BlockchainNetwork>,
+    ) -> RpcResult<Vec<KnownCounterparty>> {
+        let mut counterparties = self.get_known_counterparties(&sender).await?;
+        if let Some(network) = network {
+            counterparties.retain(|known| known.network == network);
+        }
+        Ok(counterparties)
     }
+}
 
+#[async_trait]
+impl RouterServer for TransactionHandler {
     async fn receive_confirmed_tx(&self, pending: PendingSubscriptionSink) -> SubscriptionResult {
         let sink = pending.accept().await?;
-        // fetch the confirmed and ready to be simulated txn
-        while self.get_total_number_of_simulated_tx().await != 0 {
-            if let Some(tx_simulated) = self.get_simulate_tx().await {
-                sink.send(SubscriptionMessage::from_json(&tx_simulated)?)
-                    .await?;
+        // deliveries of this feed waiting for acknowledgement with their lease deadline
+        let mut unacked = VecDeque::<(u64, u64)>::new();
+        loop {
+            // register for wake ups before looking at the queue so no enqueue is missed
+            let notified = self.simulation_notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let now = now_millis();
+            let mut still_unacked = VecDeque::new();
+            for (delivery_id, deadline) in unacked.drain(..) {
+                if deadline > now && self.is_simulate_tx_queued(delivery_id).await? {
+                    still_unacked.push_back((delivery_id, deadline));
+                }
+            }
+            unacked = still_unacked;
+
+            // backpressure, stop leasing until the consumer catches up with acknowledgements
+            if unacked.len() < MAX_UNACKED_SIMULATIONS {
+                if let Some(delivery) = self.lease_simulate_tx(now).await? {
+                    unacked.push_back((
+                        delivery.delivery_id,
+                        now + SIMULATION_LEASE.as_millis() as u64,
+                    ));
+                    sink.send(SubscriptionMessage::from_json(&delivery)?)
+                        .await?;
+                    continue;
+                }
+            }
+
+            tokio::select! {
+                _ = sink.closed() => break,
+                _ = &mut notified => {},
+                // expired leases are delivered again
+                _ = tokio::time::sleep(LEASE_CHECK_INTERVAL) => {},
             }
         }
+        tracing::info!("confirmed tx feed closed");
         Ok(())
     }
+
+    async fn ack_confirmed_tx(&self, delivery_id: u64) -> RpcResult<()> {
+        if self.ack_simulate_tx(delivery_id).await? {
+            tracing::info!("simulation tx {delivery_id} acknowledged");
+            Ok(())
+        } else {
+            Err(VaneError::DeliveryNotFound { delivery_id }.into())
+        }
+    }
+
+    async fn report_tx_status(&self, tx_id: TxId, status: TxExecutionStatus) -> RpcResult<()> {
+        let tx = self
+            .find_transaction(&tx_id)
+            .await?
+            .ok_or(VaneError::TxNotFound {
+                tx_id: tx_id.clone(),
+            })?;
+        let tx_key = TxKey::from(&tx);
+        // only transactions confirmed by both parties are handed to the router
+        let confirmation_status = self
+            .get_confirmation_transaction_data(tx_key.clone())
+            .await?
+            .map(|tx_confirmation| tx_confirmation.get_confirmation_status())
+            .unwrap_or(ConfirmationStatus::WaitingForReceiver);
+        if confirmation_status != ConfirmationStatus::Ready {
+            return Err(VaneError::InvalidState {
+                tx_id,
+                expected: ConfirmationStatus::Ready,
+                actual: confirmation_status,
+            }
+            .into());
+        }
+
+        tracing::info!("transaction {tx_id} executed: {status:?}");
+        self.db
+            .lock()
+            .await
+            .set_execution_status(tx_key.clone(), status.encode())
+            .map_err(storage_error)?;
+        self.hub.publish(
+            vec![tx.sender_address, tx.receiver_address],
+            TxEvent::Executed {
+                tx_id,
+                multi_id: tx_key.multi_id,
+                status,
+            },
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MockDB;
+    use jsonrpsee::server::{ServerBuilder, ServerHandle};
+    use jsonrpsee::types::error::CallError;
+    use jsonrpsee::ws_client::{WsClient, WsClientBuilder};
+    use primitives::AddressRisk;
+    use sp_core::crypto::Ss58AddressFormat;
+    use sp_core::{sr25519, Pair};
+    use subxt::utils::MultiAddress;
+
+    fn handler() -> TransactionHandler {
+        TransactionHandler::new(
+            Box::new(MockDB::default()),
+            DEFAULT_MAX_FAILED_CONFIRMATIONS,
+            AddressRiskAnalyzer::default(),
+        )
+    }
+
+    /// Serve the handler public and router methods on a free local port with a client connected to it
+    async fn serve(handler: TransactionHandler) -> (WsClient, ServerHandle) {
+        let server = ServerBuilder::new().build("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", server.local_addr().unwrap());
+        let mut module = TransactionServer::into_rpc(handler.clone());
+        module.merge(RouterServer::into_rpc(handler)).unwrap();
+        let handle = server.start(module).unwrap();
+        let client = WsClientBuilder::default().build(url).await.unwrap();
+        (client, handle)
+    }
+
+    fn pair(seed: u8) -> sr25519::Pair {
+        sr25519::Pair::from_seed(&[seed; 32])
+    }
+
+    fn account(pair: &sr25519::Pair) -> MultiId {
+        VaneMultiAddress::Id(AccountId32(pair.public().0))
+    }
+
+    fn sign(pair: &sr25519::Pair, payload: &ConfirmationPayload) -> Vec<u8> {
+        pair.sign(&payload.encode()).0.to_vec()
+    }
+
+    /// JSON-RPC error code of a failed call
+    fn error_code<T: std::fmt::Debug>(result: RpcResult<T>) -> i32 {
+        match result.unwrap_err() {
+            jsonrpsee::core::Error::Call(CallError::Custom(err)) => err.code(),
+            err => panic!("not a vane error: {err}"),
+        }
+    }
+
+    async fn submit(
+        handler: &TransactionHandler,
+        sender: &sr25519::Pair,
+        receiver: &sr25519::Pair,
+    ) -> TxObject {
+        let submission = handler
+            .submit_transaction(
+                VaneCallData::SubstrateCallData { amount: 10 },
+                account(sender),
+                account(receiver),
+                BlockchainNetwork::Polkadot,
+            )
+            .await
+            .unwrap();
+        handler
+            .get_transaction_data(TxKey::new(submission.multi_id, submission.tx_id))
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    async fn confirm(
+        handler: &TransactionHandler,
+        tx: &TxObject,
+        role: ConfirmationRole,
+        signer: &sr25519::Pair,
+    ) -> RpcResult<()> {
+        let signature = sign(signer, &ConfirmationPayload::new(tx, role));
+        match role {
+            ConfirmationRole::Receiver => {
+                handler
+                    .receiver_confirmation(
+                        account(signer),
+                        tx.get_multi_id(),
+                        tx.get_tx_id(),
+                        signature,
+                        tx.network.clone(),
+                    )
+                    .await
+            }
+            ConfirmationRole::Sender => {
+                handler
+                    .sender_confirmation(
+                        account(signer),
+                        tx.get_multi_id(),
+                        tx.get_tx_id(),
+                        signature,
+                        tx.network.clone(),
+                    )
+                    .await
+            }
+        }
+    }
+
+    async fn status(handler: &TransactionHandler, tx: &TxObject) -> Option<ConfirmationStatus> {
+        handler
+            .get_confirmation_transaction_data(TxKey::from(tx))
+            .await
+            .unwrap()
+            .map(|tx_confirmation| tx_confirmation.get_confirmation_status())
+    }
+
+    async fn revert(
+        handler: &TransactionHandler,
+        tx: &TxObject,
+        signer: &sr25519::Pair,
+        payload: &ConfirmationPayload,
+    ) -> RpcResult<()> {
+        handler
+            .sender_revert_transaction(
+                account(signer),
+                tx.get_multi_id(),
+                tx.get_tx_id(),
+                sign(signer, payload),
+                tx.network.clone(),
+            )
+            .await
+    }
+
+    // SUBMISSION
+
+    #[tokio::test]
+    async fn evm_call_data_is_refused_on_polkadot() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let submission = handler
+            .submit_transaction(
+                VaneCallData::EthereumCallData { amount: 10 },
+                account(&sender),
+                account(&receiver),
+                BlockchainNetwork::Polkadot,
+            )
+            .await;
+        assert_eq!(error_code(submission), -32007);
+        // nothing recorded for either party
+        assert!(handler
+            .get_pending_multi_ids(account(&sender))
+            .await
+            .unwrap()
+            .is_none());
+        assert!(handler
+            .get_pending_multi_ids(account(&receiver))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn substrate_call_data_is_refused_on_ethereum() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let submission = handler
+            .submit_transaction(
+                VaneCallData::SubstrateCallData { amount: 10 },
+                account(&sender),
+                account(&receiver),
+                BlockchainNetwork::Ethereum,
+            )
+            .await;
+        assert_eq!(error_code(submission), -32007);
+        assert!(handler
+            .get_pending_multi_ids(account(&sender))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    /// `Raw` SS58 text of the account as wallets of the network with `prefix` show it
+    fn raw_ss58(pair: &sr25519::Pair, prefix: u16) -> MultiId {
+        let text = SpAccountId32::new(pair.public().0)
+            .to_ss58check_with_version(Ss58AddressFormat::custom(prefix));
+        VaneMultiAddress::Raw(text.into_bytes())
+    }
+
+    #[tokio::test]
+    async fn raw_ss58_receiver_confirms_the_transaction() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let submission = handler
+            .submit_transaction(
+                VaneCallData::SubstrateCallData { amount: 10 },
+                account(&sender),
+                raw_ss58(&receiver, 0),
+                BlockchainNetwork::Polkadot,
+            )
+            .await
+            .unwrap();
+        assert!(submission.warnings.is_empty());
+        let tx = handler
+            .get_transaction_data(TxKey::new(submission.multi_id, submission.tx_id))
+            .await
+            .unwrap()
+            .unwrap();
+        // stored under the account the receiver subscribes and confirms with
+        assert_eq!(tx.receiver_address, account(&receiver));
+
+        confirm(&handler, &tx, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+        confirm(&handler, &tx, ConfirmationRole::Sender, &sender)
+            .await
+            .unwrap();
+        assert_eq!(status(&handler, &tx).await, Some(ConfirmationStatus::Ready));
+    }
+
+    #[tokio::test]
+    async fn raw_ss58_of_another_network_is_flagged_and_decoded() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let submission = handler
+            .submit_transaction(
+                VaneCallData::SubstrateCallData { amount: 10 },
+                account(&sender),
+                raw_ss58(&receiver, 2),
+                BlockchainNetwork::Polkadot,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            submission
+                .warnings
+                .iter()
+                .map(|warning| warning.risk.clone())
+                .collect::<Vec<_>>(),
+            vec![AddressRisk::ForeignSs58Prefix {
+                prefix: 2,
+                network: Some(BlockchainNetwork::Kusama),
+            }]
+        );
+        let tx = handler
+            .get_transaction_data(TxKey::new(submission.multi_id, submission.tx_id))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tx.receiver_address, account(&receiver));
+    }
+
+    // SUBSCRIPTIONS
+
+    #[tokio::test]
+    async fn receiver_subscription_sends_the_snapshot_then_events() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let pending = submit(&handler, &sender, &receiver).await;
+        let (client, _server) = serve(handler.clone()).await;
+
+        let mut updates = client
+            .subscribe_tx_confirmation(account(&receiver))
+            .await
+            .unwrap();
+        assert_eq!(
+            updates.next().await.unwrap().unwrap(),
+            SubscriptionUpdate::Snapshot(vec![pending])
+        );
+
+        let tx = submit(&handler, &sender, &receiver).await;
+        assert_eq!(
+            updates.next().await.unwrap().unwrap(),
+            SubscriptionUpdate::Event(Box::new(TxEvent::Submitted(tx)))
+        );
+    }
+
+    #[tokio::test]
+    async fn sender_subscription_sends_the_snapshot_then_events() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+        let (client, _server) = serve(handler.clone()).await;
+
+        let mut updates = client
+            .subscribe_tx_confirmation_sender(account(&sender))
+            .await
+            .unwrap();
+        // nothing confirmed by the receiver yet
+        assert_eq!(
+            updates.next().await.unwrap().unwrap(),
+            SubscriptionUpdate::Snapshot(vec![])
+        );
+
+        confirm(&handler, &tx, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+        let tx_confirmation = handler
+            .get_confirmation_transaction_data(TxKey::from(&tx))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            updates.next().await.unwrap().unwrap(),
+            SubscriptionUpdate::Event(Box::new(TxEvent::ReceiverConfirmed(tx_confirmation)))
+        );
+    }
+
+    // SIMULATION FEED
+
+    #[tokio::test]
+    async fn confirmed_feed_stops_at_max_unacked_deliveries() {
+        let handler = handler();
+        let tx = TxObject::new(
+            VaneCallData::SubstrateCallData { amount: 10 },
+            MultiAddress::Id(AccountId32(pair(1).public().0)),
+            MultiAddress::Id(AccountId32(pair(2).public().0)),
+            BlockchainNetwork::Polkadot,
+        );
+        let tx_simulation: TxSimulationObject = TxConfirmationObject::from(tx).into();
+        for _ in 0..=MAX_UNACKED_SIMULATIONS {
+            let mut db = handler.db.lock().await;
+            db.push_simulation(tx_simulation.encode()).unwrap();
+        }
+        let (client, _server) = serve(handler.clone()).await;
+
+        let mut feed = client.receive_confirmed_tx().await.unwrap();
+        let mut delivery_ids = Vec::new();
+        for _ in 0..MAX_UNACKED_SIMULATIONS {
+            delivery_ids.push(feed.next().await.unwrap().unwrap().delivery_id);
+        }
+        // the last entry waits until the consumer acknowledges a delivery
+        let pending = tokio::time::timeout(Duration::from_millis(500), feed.next()).await;
+        assert!(pending.is_err());
+
+        client.ack_confirmed_tx(delivery_ids[0]).await.unwrap();
+        let delivery = feed.next().await.unwrap().unwrap();
+        assert!(!delivery_ids.contains(&delivery.delivery_id));
+        // acknowledged deliveries can not be acknowledged twice
+        assert!(client.ack_confirmed_tx(delivery_ids[0]).await.is_err());
+    }
+
+    // STATE TRANSITIONS
+
+    #[tokio::test]
+    async fn sender_confirmation_queues_the_transaction_once() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+        confirm(&handler, &tx, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+        confirm(&handler, &tx, ConfirmationRole::Sender, &sender)
+            .await
+            .unwrap();
+
+        let replayed = confirm(&handler, &tx, ConfirmationRole::Sender, &sender).await;
+
+        assert_eq!(error_code(replayed), -32003);
+        assert_eq!(handler.db.lock().await.simulation_len().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn expiry_keeps_confirmed_transactions() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let confirmed = submit(&handler, &sender, &receiver).await;
+        let waiting = submit(&handler, &sender, &receiver).await;
+        confirm(&handler, &confirmed, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+        confirm(&handler, &confirmed, ConfirmationRole::Sender, &sender)
+            .await
+            .unwrap();
+
+        let expired = handler
+            .expire_transactions(waiting.expires_at().max(confirmed.expires_at()))
+            .await
+            .unwrap();
+
+        assert_eq!(expired, 1);
+        assert_eq!(
+            status(&handler, &confirmed).await,
+            Some(ConfirmationStatus::Ready)
+        );
+        assert_eq!(
+            status(&handler, &waiting).await,
+            Some(ConfirmationStatus::Expired)
+        );
+        // an expired transaction can not be confirmed anymore
+        let late = confirm(&handler, &waiting, ConfirmationRole::Receiver, &receiver).await;
+        assert!(late.is_err());
+    }
+
+    #[tokio::test]
+    async fn only_transactions_in_their_confirmation_phase_are_indexed_for_expiry() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let confirmed = submit(&handler, &sender, &receiver).await;
+        let reverted = submit(&handler, &sender, &receiver).await;
+        let waiting = submit(&handler, &sender, &receiver).await;
+        let due = |tx: &TxObject| (tx.expires_at(), TxKey::from(tx));
+        let indexed = || async {
+            handler
+                .db
+                .lock()
+                .await
+                .expiring_transactions(u64::MAX)
+                .unwrap()
+        };
+        assert_eq!(indexed().await.len(), 3);
+
+        confirm(&handler, &confirmed, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+        confirm(&handler, &confirmed, ConfirmationRole::Sender, &sender)
+            .await
+            .unwrap();
+        revert(
+            &handler,
+            &reverted,
+            &sender,
+            &ConfirmationPayload::revert(&reverted),
+        )
+        .await
+        .unwrap();
+        assert_eq!(indexed().await, vec![due(&waiting)]);
+        // nothing is due before the confirmation window passed
+        let db = handler.db.lock().await;
+        assert!(db
+            .expiring_transactions(waiting.expires_at() - 1)
+            .unwrap()
+            .is_empty());
+        drop(db);
+
+        assert_eq!(
+            handler
+                .expire_transactions(waiting.expires_at())
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(indexed().await.is_empty());
+    }
+
+    // CONFIRMATIONS
+
+    #[tokio::test]
+    async fn only_the_parties_confirm_their_role() {
+        let handler = handler();
+        let (sender, receiver, stranger) = (pair(1), pair(2), pair(3));
+        let tx = submit(&handler, &sender, &receiver).await;
+
+        // a third party signing for itself is neither the receiver nor the sender
+        let as_receiver = confirm(&handler, &tx, ConfirmationRole::Receiver, &stranger).await;
+        assert_eq!(error_code(as_receiver), -32012);
+        assert_eq!(status(&handler, &tx).await, None);
+        // the sender can not confirm on behalf of the receiver either
+        let sender_as_receiver = confirm(&handler, &tx, ConfirmationRole::Receiver, &sender).await;
+        assert_eq!(error_code(sender_as_receiver), -32012);
+
+        confirm(&handler, &tx, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+        let as_sender = confirm(&handler, &tx, ConfirmationRole::Sender, &stranger).await;
+        assert_eq!(error_code(as_sender), -32010);
+        assert_eq!(
+            status(&handler, &tx).await,
+            Some(ConfirmationStatus::WaitingForSender)
+        );
+    }
+
+    #[tokio::test]
+    async fn replayed_receiver_confirmation_is_rejected() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+        confirm(&handler, &tx, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+        let replayed = confirm(&handler, &tx, ConfirmationRole::Receiver, &receiver).await;
+        assert_eq!(error_code(replayed), -32003);
+
+        confirm(&handler, &tx, ConfirmationRole::Sender, &sender)
+            .await
+            .unwrap();
+        // replaying the receiver signature does not move the confirmed transaction back
+        let replayed = confirm(&handler, &tx, ConfirmationRole::Receiver, &receiver).await;
+        assert_eq!(error_code(replayed), -32003);
+        assert_eq!(status(&handler, &tx).await, Some(ConfirmationStatus::Ready));
+        assert_eq!(handler.db.lock().await.simulation_len().unwrap(), 1);
+    }
+
+    // LOCKING
+
+    #[tokio::test]
+    async fn transaction_locks_after_max_failed_confirmations() {
+        let handler = TransactionHandler::new(
+            Box::new(MockDB::default()),
+            2,
+            AddressRiskAnalyzer::default(),
+        );
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+        let mut events = handler.hub.subscribe();
+        // the receiver address with a signature of another key
+        let forged = || {
+            handler.receiver_confirmation(
+                account(&receiver),
+                tx.get_multi_id(),
+                tx.get_tx_id(),
+                sign(
+                    &pair(3),
+                    &ConfirmationPayload::new(&tx, ConfirmationRole::Receiver),
+                ),
+                tx.network.clone(),
+            )
+        };
+
+        assert_eq!(error_code(forged().await), -32009);
+        assert_eq!(status(&handler, &tx).await, None);
+        assert_eq!(error_code(forged().await), -32009);
+        assert_eq!(
+            status(&handler, &tx).await,
+            Some(ConfirmationStatus::Locked)
+        );
+
+        let notification = events.recv().await.unwrap();
+        assert!(notification.concerns(&account(&sender)));
+        assert!(notification.concerns(&account(&receiver)));
+        assert_eq!(
+            notification.event,
+            TxEvent::Locked {
+                tx_id: tx.get_tx_id(),
+                multi_id: tx.get_multi_id(),
+            }
+        );
+        // even a valid confirmation is refused once locked
+        let late = confirm(&handler, &tx, ConfirmationRole::Receiver, &receiver).await;
+        assert_eq!(error_code(late), -32005);
+    }
+
+    /// Lock the transaction with forged receiver confirmations, the handler locks after 2
+    async fn lock(handler: &TransactionHandler, tx: &TxObject, receiver: &sr25519::Pair) {
+        for _ in 0..2 {
+            let forged = handler
+                .receiver_confirmation(
+                    account(receiver),
+                    tx.get_multi_id(),
+                    tx.get_tx_id(),
+                    sign(
+                        &pair(3),
+                        &ConfirmationPayload::new(tx, ConfirmationRole::Receiver),
+                    ),
+                    tx.network.clone(),
+                )
+                .await;
+            assert_eq!(error_code(forged), -32009);
+        }
+        assert_eq!(status(handler, tx).await, Some(ConfirmationStatus::Locked));
+    }
+
+    fn locking_handler() -> TransactionHandler {
+        TransactionHandler::new(
+            Box::new(MockDB::default()),
+            2,
+            AddressRiskAnalyzer::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn sender_reverts_a_locked_transaction() {
+        let handler = locking_handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+        lock(&handler, &tx, &receiver).await;
+
+        revert(&handler, &tx, &sender, &ConfirmationPayload::revert(&tx))
+            .await
+            .unwrap();
+        assert_eq!(
+            handler
+                .get_transaction_data(TxKey::from(&tx))
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn locked_transaction_expires() {
+        let handler = locking_handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+        lock(&handler, &tx, &receiver).await;
+
+        assert_eq!(
+            handler.expire_transactions(tx.expires_at()).await.unwrap(),
+            1
+        );
+        assert_eq!(
+            status(&handler, &tx).await,
+            Some(ConfirmationStatus::Expired)
+        );
+    }
+
+    #[tokio::test]
+    async fn address_without_a_public_key_is_not_counted() {
+        let handler = locking_handler();
+        let sender = pair(1);
+        // not an sr25519 public key, no signature can match it
+        let receiver: MultiId = VaneMultiAddress::Id(AccountId32([0xff; 32]));
+        let submission = handler
+            .submit_transaction(
+                VaneCallData::SubstrateCallData { amount: 10 },
+                account(&sender),
+                receiver.clone(),
+                BlockchainNetwork::Polkadot,
+            )
+            .await
+            .unwrap();
+        let tx = handler
+            .get_transaction_data(TxKey::new(submission.multi_id, submission.tx_id))
+            .await
+            .unwrap()
+            .unwrap();
+
+        for _ in 0..3 {
+            let confirmation = handler
+                .receiver_confirmation(
+                    receiver.clone(),
+                    tx.get_multi_id(),
+                    tx.get_tx_id(),
+                    vec![0; 64],
+                    tx.network.clone(),
+                )
+                .await;
+            assert_eq!(error_code(confirmation), -32009);
+        }
+        assert_eq!(status(&handler, &tx).await, None);
+        handler.ensure_not_locked(&TxKey::from(&tx)).await.unwrap();
+    }
+
+    // COUNTERPARTIES
+
+    async fn is_trusted(handler: &TransactionHandler, tx: &TxObject) -> bool {
+        handler
+            .get_confirmation_transaction_data(TxKey::from(tx))
+            .await
+            .unwrap()
+            .unwrap()
+            .is_trusted_receiver()
+    }
+
+    #[tokio::test]
+    async fn receiver_is_trusted_after_a_transaction_confirmed_by_both_parties() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let first = submit(&handler, &sender, &receiver).await;
+        confirm(&handler, &first, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+        assert!(!is_trusted(&handler, &first).await);
+
+        // a receiver confirmation alone does not make the receiver known
+        let second = submit(&handler, &sender, &receiver).await;
+        confirm(&handler, &second, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+        assert!(!is_trusted(&handler, &second).await);
+
+        confirm(&handler, &first, ConfirmationRole::Sender, &sender)
+            .await
+            .unwrap();
+        let known = handler
+            .known_counterparties(account(&sender), Some(BlockchainNetwork::Polkadot))
+            .await
+            .unwrap();
+        assert_eq!(known.len(), 1);
+        assert_eq!(known[0].receiver, account(&receiver));
+        assert_eq!(known[0].confirmed_txs, 1);
+        assert!(handler
+            .is_known_counterparty(
+                &account(&sender),
+                &account(&receiver),
+                &BlockchainNetwork::Polkadot
+            )
+            .await
+            .unwrap());
+        // the receiver is known on the network it was paid on only
+        assert!(!handler
+            .is_known_counterparty(
+                &account(&sender),
+                &account(&receiver),
+                &BlockchainNetwork::Kusama
+            )
+            .await
+            .unwrap());
+
+        let third = submit(&handler, &sender, &receiver).await;
+        confirm(&handler, &third, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+        assert!(is_trusted(&handler, &third).await);
+
+        confirm(&handler, &second, ConfirmationRole::Sender, &sender)
+            .await
+            .unwrap();
+        let known = handler
+            .known_counterparties(account(&sender), None)
+            .await
+            .unwrap();
+        assert_eq!(known.len(), 1);
+        assert_eq!(known[0].confirmed_txs, 2);
+    }
+
+    #[tokio::test]
+    async fn rejected_confirmations_record_no_counterparty() {
+        let handler = handler();
+        let (sender, receiver, stranger) = (pair(1), pair(2), pair(3));
+        let tx = submit(&handler, &sender, &receiver).await;
+
+        assert!(
+            confirm(&handler, &tx, ConfirmationRole::Receiver, &stranger)
+                .await
+                .is_err()
+        );
+        confirm(&handler, &tx, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+        assert!(confirm(&handler, &tx, ConfirmationRole::Sender, &stranger)
+            .await
+            .is_err());
+
+        assert!(handler
+            .known_counterparties(account(&sender), None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(handler
+            .known_counterparties(account(&stranger), None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    // REVERT
+
+    #[tokio::test]
+    async fn sender_reverts_with_a_signed_revert_payload() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+        confirm(&handler, &tx, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+
+        revert(&handler, &tx, &sender, &ConfirmationPayload::revert(&tx))
+            .await
+            .unwrap();
+
+        let tx_key = TxKey::from(&tx);
+        assert_eq!(handler.get_transaction_data(tx_key).await.unwrap(), None);
+        assert_eq!(
+            handler
+                .get_reverted_transactions(account(&sender))
+                .await
+                .unwrap(),
+            vec![tx]
+        );
+    }
+
+    #[tokio::test]
+    async fn revert_requires_the_sender_signature_over_the_revert_payload() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+
+        // a confirmation signature is not a revert signature
+        let confirmation = ConfirmationPayload::new(&tx, ConfirmationRole::Sender);
+        assert_eq!(
+            error_code(revert(&handler, &tx, &sender, &confirmation).await),
+            -32009
+        );
+        // the sender address signed by another key
+        let signature = sign(&pair(3), &ConfirmationPayload::revert(&tx));
+        let result = handler
+            .sender_revert_transaction(
+                account(&sender),
+                tx.get_multi_id(),
+                tx.get_tx_id(),
+                signature,
+                tx.network.clone(),
+            )
+            .await;
+        assert_eq!(error_code(result), -32009);
+        assert!(handler
+            .get_transaction_data(TxKey::from(&tx))
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn confirmed_transaction_can_not_be_reverted() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+        confirm(&handler, &tx, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+        confirm(&handler, &tx, ConfirmationRole::Sender, &sender)
+            .await
+            .unwrap();
+
+        let result = revert(&handler, &tx, &sender, &ConfirmationPayload::revert(&tx)).await;
+
+        assert_eq!(error_code(result), -32003);
+        assert!(handler.is_simulate_tx_queued(1).await.unwrap());
+    }
+
+    // TRANSACTION STATE
+
+    async fn states(
+        handler: &TransactionHandler,
+        sender: &sr25519::Pair,
+        tx_id: Option<TxId>,
+    ) -> Vec<TxStateObject> {
+        let response = handler
+            .get_transaction(account(sender), tx_id, Some(ResponseEncoding::Json))
+            .await
+            .unwrap();
+        serde_json::from_value(response).unwrap()
+    }
+
+    #[tokio::test]
+    async fn transaction_state_is_scale_encoded_by_default() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+
+        let response = handler
+            .get_transaction(account(&sender), None, None)
+            .await
+            .unwrap();
+        let encoded: Bytes = serde_json::from_value(response).unwrap();
+        let scale = Vec::<TxStateObject>::decode(&mut &encoded[..]).unwrap();
+        let json = states(&handler, &sender, None).await;
+
+        assert_eq!(scale.len(), 1);
+        assert_eq!(scale[0].tx, tx);
+        assert_eq!(
+            scale[0].confirmation_status,
+            ConfirmationStatus::WaitingForReceiver
+        );
+        // both encodings carry the same view
+        assert_eq!(scale.encode(), json.encode());
+    }
+
+    #[tokio::test]
+    async fn transaction_state_narrows_down_to_the_tx_id() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        submit(&handler, &sender, &receiver).await;
+        let second = submit(&handler, &sender, &receiver).await;
+
+        let tx_states = states(&handler, &sender, Some(second.get_tx_id())).await;
+        assert_eq!(tx_states.len(), 1);
+        assert_eq!(tx_states[0].tx, second);
+        assert_eq!(states(&handler, &sender, None).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn unknown_transaction_is_not_found() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+
+        // only the sender of the transaction can look it up
+        let lookup = handler
+            .get_transaction(account(&receiver), Some(tx.get_tx_id()), None)
+            .await;
+        assert_eq!(error_code(lookup), -32001);
+        // without a tx_id an empty list is returned instead
+        assert!(states(&handler, &receiver, None).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reverted_transaction_state_is_reverted() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+        revert(&handler, &tx, &sender, &ConfirmationPayload::revert(&tx))
+            .await
+            .unwrap();
+
+        let tx_states = states(&handler, &sender, Some(tx.get_tx_id())).await;
+        assert_eq!(tx_states.len(), 1);
+        assert_eq!(tx_states[0].tx, tx);
+        assert_eq!(
+            tx_states[0].confirmation_status,
+            ConfirmationStatus::Reverted
+        );
+        assert_eq!(tx_states[0].simulation_status, SimulationStatus::NotReady);
+    }
+
+    #[tokio::test]
+    async fn expired_transaction_state_is_expired() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+        assert_eq!(
+            handler.expire_transactions(tx.expires_at()).await.unwrap(),
+            1
+        );
+
+        let tx_states = states(&handler, &sender, Some(tx.get_tx_id())).await;
+        assert_eq!(tx_states.len(), 1);
+        assert_eq!(tx_states[0].tx.lifetime_status, LifetimeStatus::Invalid);
+        assert_eq!(
+            tx_states[0].confirmation_status,
+            ConfirmationStatus::Expired
+        );
+    }
+
+    // EXECUTION
+
+    #[tokio::test]
+    async fn transaction_state_shows_the_latest_execution_status() {
+        let handler = handler();
+        let (sender, receiver) = (pair(1), pair(2));
+        let tx = submit(&handler, &sender, &receiver).await;
+        confirm(&handler, &tx, ConfirmationRole::Receiver, &receiver)
+            .await
+            .unwrap();
+        confirm(&handler, &tx, ConfirmationRole::Sender, &sender)
+            .await
+            .unwrap();
+        let state = handler.get_transaction_state(tx.clone()).await.unwrap();
+        assert_eq!(state.execution_status, None);
+
+        let tx_hash = "0x01".to_string();
+        handler
+            .report_tx_status(tx.get_tx_id(), TxExecutionStatus::Submitted { tx_hash })
+            .await
+            .unwrap();
+        let failed = TxExecutionStatus::Failed {
+            reason: "Inability to pay some fees".to_string(),
+        };
+        handler
+            .report_tx_status(tx.get_tx_id(), failed.clone())
+            .await
+            .unwrap();
+
+        let state = handler.get_transaction_state(tx).await.unwrap();
+        assert_eq!(state.execution_status, Some(failed));
+    }
+
+    #[tokio::test]
+    async fn execution_status_of_an_unconfirmed_transaction_is_refused() {
+        let handler = handler();
+        let tx = submit(&handler, &pair(1), &pair(2)).await;
+        let tx_hash = "0x01".to_string();
+
+        let result = handler
+            .report_tx_status(tx.get_tx_id(), TxExecutionStatus::Submitted { tx_hash })
+            .await;
+
+        assert_eq!(error_code(result), -32003);
+        let state = handler.get_transaction_state(tx).await.unwrap();
+        assert_eq!(state.execution_status, None);
+    }
 }
@@ -1,63 +1,94 @@
 use anyhow::Ok;
-use clap::Parser;
-use jsonrpsee::server::ServerBuilder;
-use std::collections::BTreeMap;
-use std::collections::HashMap;
-use std::collections::VecDeque;
+use clap::{Parser, ValueEnum};
+use jsonrpsee::server::{ServerBuilder, ServerHandle};
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use tower_http::validate_request::ValidateRequestHeaderLayer;
 
+mod db;
+mod evm;
 mod handlers;
+mod notification;
+mod risk;
+mod solana;
 mod traits;
+mod verifier;
 
-use handlers::MockDB;
-use handlers::TransactionHandler;
-use tokio::sync::Mutex;
-use traits::TransactionServer;
+use db::{MockDB, SledDB};
+use handlers::{TransactionHandler, DEFAULT_MAX_FAILED_CONFIRMATIONS};
+use risk::AddressRiskAnalyzer;
+use traits::{RouterServer, TransactionServer, TxStorage};
 
 use crate::handlers::init_tracing;
 
+/// Storage backends the server can be started with
+#[derive(ValueEnum, Clone, Debug)]
+enum DbBackend {
+    /// In memory maps, everything is lost on restart
+    Memory,
+    /// Embedded on-disk database
+    Sled,
+}
+
 /// Address Verification layer cli server arguments
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct AvLayerServerCli {
     /// Name of the server
-    #[arg(short, long)]
+    #[arg(short, long, default_value = "vane-av-layer")]
     name: String,
     /// url to listen to
-    #[arg(short, long)]
+    #[arg(short, long, default_value = "127.0.0.1:8000")]
     url: String,
-}
-
-pub struct ServerProfile {
-    name: String,
-    port: u16,
+    /// url the network router layer connects to, serving the confirmed transaction feed
+    #[arg(long, default_value = "127.0.0.1:8001")]
+    router_url: String,
+    /// File holding the secret the network router layer authenticates with on `--router-url`
+    #[arg(long)]
+    router_secret_file: PathBuf,
+    /// Storage backend for transactions and confirmations
+    #[arg(long, value_enum, default_value_t = DbBackend::Memory)]
+    db: DbBackend,
+    /// Directory of the on-disk database, used with `--db sled`
+    #[arg(long, default_value = "vane-av-db")]
+    db_path: PathBuf,
+    /// Confirmations failing signature verification after which a transaction is locked
+    #[arg(long, default_value_t = DEFAULT_MAX_FAILED_CONFIRMATIONS)]
+    max_failed_confirmations: u32,
+    /// JSON list of known contract and exchange deposit addresses to warn about
+    #[arg(long)]
+    flagged_addresses: Option<PathBuf>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     init_tracing()?;
-    //let args = AvLayerServerCli::parse();
+    let args = AvLayerServerCli::parse();
     // Initialise the database
-    let mock_db_transactions = BTreeMap::new();
-    let mock_db_multi_ids = BTreeMap::new();
-
-    let confirmation = BTreeMap::new();
-
-    let rpc_handler = TransactionHandler {
-        db: Arc::new(Mutex::new(MockDB {
-            transactions: mock_db_transactions,
-            multi_ids: mock_db_multi_ids,
-            confirmation,
-            reverted_transactions: BTreeMap::new(),
-            simulation: VecDeque::new(),
-            subscribed: Vec::new(),
-        })),
+    let db: Box<dyn TxStorage> = match args.db {
+        DbBackend::Memory => Box::new(MockDB::default()),
+        DbBackend::Sled => Box::new(SledDB::open(&args.db_path)?),
     };
+    tracing::info!("{} using {:?} storage backend", args.name, args.db);
+
+    let risk = match &args.flagged_addresses {
+        Some(path) => AddressRiskAnalyzer::load(path)?,
+        None => AddressRiskAnalyzer::default(),
+    };
+
+    let rpc_handler = TransactionHandler::new(db, args.max_failed_confirmations, risk);
+    // expire transactions whose confirmation window passed
+    tokio::spawn(rpc_handler.clone().run_expiry_reaper());
     println!("Starting server");
 
+    let router_secret = read_router_secret(&args.router_secret_file)?;
+    // kept alive as the router server stops once its handle is dropped
+    let (router_addr, _router_handle) =
+        start_router_server(rpc_handler.clone(), args.router_url, &router_secret).await?;
+    tracing::info!("serving the network router layer on {router_addr}");
+
     // Initialize the server
-    run_rpc_server(rpc_handler, "127.0.0.1:8000".to_owned()).await?;
+    run_rpc_server(rpc_handler, args.url).await?;
 
     Ok(())
 }
@@ -73,7 +104,7 @@ async fn run_rpc_server(
     let server = server_builder.build(url).await?;
 
     let addr = server.local_addr()?;
-    let handle = server.start(rpc_handler.into_rpc())?;
+    let handle = server.start(TransactionServer::into_rpc(rpc_handler))?;
 
     // In this example we don't care about doing shutdown so let's it run forever.
     // You may use the `ServerHandle` to shut it down or manage it yourself.
@@ -84,3 +115,154 @@ async fn run_rpc_server(
 
     Ok(addr)
 }
+
+/// Secret of the router endpoint, the trimmed content of `path`
+fn read_router_secret(path: &Path) -> anyhow::Result<String> {
+    let secret = std::fs::read_to_string(path)?.trim().to_string();
+    // sent as an `Authorization` header value
+    if secret.is_empty() || !secret.chars().all(|c| c.is_ascii_graphic()) {
+        anyhow::bail!(
+            "router secret in {} must be non empty printable ascii",
+            path.display()
+        );
+    }
+    Ok(secret)
+}
+
+/// Serve the public and the `Router` methods to the network router layer
+/// connections without `Authorization: Bearer <secret>` are refused before the websocket upgrade
+async fn start_router_server(
+    rpc_handler: TransactionHandler,
+    url: String,
+    secret: &str,
+) -> anyhow::Result<(SocketAddr, ServerHandle)> {
+    let middleware = tower::ServiceBuilder::new().layer(ValidateRequestHeaderLayer::bearer(secret));
+    let server = ServerBuilder::new()
+        .set_middleware(middleware)
+        .build(url)
+        .await?;
+
+    let addr = server.local_addr()?;
+    let mut module = TransactionServer::into_rpc(rpc_handler.clone());
+    module.merge(RouterServer::into_rpc(rpc_handler))?;
+    let handle = server.start(module)?;
+    Ok((addr, handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::types::error::{CallError, METHOD_NOT_FOUND_CODE};
+    use jsonrpsee::ws_client::{HeaderMap, HeaderValue, WsClientBuilder};
+    use primitives::{BlockchainNetwork, TxExecutionStatus, TxId, VaneCallData, VaneMultiAddress};
+    use risk::AddressRiskAnalyzer;
+    use subxt::utils::AccountId32;
+    use traits::RouterClient;
+
+    const SECRET: &str = "router-secret";
+
+    fn handler() -> TransactionHandler {
+        TransactionHandler::new(
+            Box::new(MockDB::default()),
+            DEFAULT_MAX_FAILED_CONFIRMATIONS,
+            AddressRiskAnalyzer::default(),
+        )
+    }
+
+    fn unknown_tx_id() -> TxId {
+        let account = VaneMultiAddress::Id(AccountId32([7; 32]));
+        let call = VaneCallData::SubstrateCallData { amount: 10 };
+        TxId::derive(
+            &account,
+            &account,
+            &BlockchainNetwork::Polkadot,
+            &call,
+            0,
+            &[0; 16],
+        )
+    }
+
+    fn failed() -> TxExecutionStatus {
+        TxExecutionStatus::Failed {
+            reason: "insufficient balance".into(),
+        }
+    }
+
+    fn bearer(secret: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            HeaderValue::from_str(&format!("Bearer {secret}")).unwrap(),
+        );
+        headers
+    }
+
+    #[tokio::test]
+    async fn router_endpoint_refuses_connections_without_the_secret() {
+        let (addr, _handle) = start_router_server(handler(), "127.0.0.1:0".into(), SECRET)
+            .await
+            .unwrap();
+        let url = format!("ws://{addr}");
+
+        assert!(WsClientBuilder::default().build(&url).await.is_err());
+        assert!(WsClientBuilder::default()
+            .set_headers(bearer("not-the-secret"))
+            .build(&url)
+            .await
+            .is_err());
+
+        let client = WsClientBuilder::default()
+            .set_headers(bearer(SECRET))
+            .build(&url)
+            .await
+            .unwrap();
+        // reaches the handler, which does not know the delivery
+        match client.ack_confirmed_tx(7).await.unwrap_err() {
+            jsonrpsee::core::Error::Call(CallError::Custom(err)) => assert_eq!(err.code(), -32011),
+            err => panic!("not a vane error: {err}"),
+        }
+        assert!(client.receive_confirmed_tx().await.is_ok());
+        match client
+            .report_tx_status(unknown_tx_id(), failed())
+            .await
+            .unwrap_err()
+        {
+            jsonrpsee::core::Error::Call(CallError::Custom(err)) => assert_eq!(err.code(), -32001),
+            err => panic!("not a vane error: {err}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn public_endpoint_does_not_serve_the_router_methods() {
+        let server = ServerBuilder::new().build("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", server.local_addr().unwrap());
+        let _handle = server
+            .start(TransactionServer::into_rpc(handler()))
+            .unwrap();
+        let client = WsClientBuilder::default().build(&url).await.unwrap();
+
+        match client.ack_confirmed_tx(7).await.unwrap_err() {
+            jsonrpsee::core::Error::Call(CallError::Custom(err)) => {
+                assert_eq!(err.code(), METHOD_NOT_FOUND_CODE)
+            }
+            err => panic!("not a call error: {err}"),
+        }
+        assert!(client.receive_confirmed_tx().await.is_err());
+        // execution statuses are only accepted from the network router layer
+        assert!(client
+            .report_tx_status(unknown_tx_id(), failed())
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn router_secret_is_trimmed_and_must_not_be_empty() {
+        let path = std::env::temp_dir().join(format!("vane-router-secret-{}", std::process::id()));
+
+        std::fs::write(&path, format!("{SECRET}\n")).unwrap();
+        assert_eq!(read_router_secret(&path).unwrap(), SECRET);
+        std::fs::write(&path, " \n").unwrap();
+        assert!(read_router_secret(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+use crate::handlers::MultiId;
+use primitives::TxEvent;
+use tokio::sync::broadcast;
+
+/// Number of events kept for slow subscribers before they start lagging
+const EVENT_CAPACITY: usize = 1024;
+
+/// `TxEvent` addressed to the accounts involved in the transaction
+#[derive(Debug, Clone)]
+pub struct TxNotification {
+    pub addresses: Vec<MultiId>,
+    pub event: TxEvent,
+}
+
+impl TxNotification {
+    pub fn concerns(&self, address: &MultiId) -> bool {
+        self.addresses.contains(address)
+    }
+}
+
+/// Fan out of transaction events to every live subscription
+/// Each subscription filters the notifications for the address it subscribed with
+#[derive(Clone)]
+pub struct NotificationHub {
+    sender: broadcast::Sender<TxNotification>,
+}
+
+impl Default for NotificationHub {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl NotificationHub {
+    pub fn publish(&self, addresses: Vec<MultiId>, event: TxEvent) {
+        // sending only fails when there is no live subscription, nothing to deliver then
+        let _ = self.sender.send(TxNotification { addresses, event });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TxNotification> {
+        self.sender.subscribe()
+    }
+}
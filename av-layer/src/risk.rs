@@ -0,0 +1,304 @@
+use crate::handlers::MultiId;
+use primitives::{
+    AddressError, AddressRisk, AddressWarning, BlockchainNetwork, ConfirmationRole,
+    VaneMultiAddress,
+};
+use serde::Deserialize;
+use sp_core::crypto::{AccountId32 as SpAccountId32, Ss58Codec};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+/// Kind of a listed address
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FlaggedKind {
+    Contract,
+    Exchange,
+}
+
+/// Entry of the flagged addresses list
+/// `address` is written the way wallets of the `network` show it
+#[derive(Debug, Clone, Deserialize)]
+pub struct FlaggedAddress {
+    pub network: BlockchainNetwork,
+    pub address: String,
+    pub kind: FlaggedKind,
+    pub label: String,
+}
+
+/// Looks for addresses likely to lose the funds on the selected network
+/// The findings are warnings, both parties still get to review them before confirming
+#[derive(Debug, Default)]
+pub struct AddressRiskAnalyzer {
+    flagged: HashMap<BlockchainNetwork, BTreeMap<MultiId, (FlaggedKind, String)>>,
+}
+
+impl AddressRiskAnalyzer {
+    /// Analyzer flagging the addresses listed in the JSON file at `path`
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let entries: Vec<FlaggedAddress> = serde_json::from_slice(&std::fs::read(path)?)?;
+        let mut analyzer = Self::default();
+        for entry in entries {
+            analyzer.flag(entry)?;
+        }
+        Ok(analyzer)
+    }
+
+    pub fn flag(&mut self, entry: FlaggedAddress) -> anyhow::Result<()> {
+        let address = VaneMultiAddress::parse(&entry.address, &entry.network)
+            .map_err(|err| anyhow::anyhow!("Invalid flagged address {}: {err}", entry.address))?;
+        self.flagged
+            .entry(entry.network)
+            .or_default()
+            .insert(address, (entry.kind, entry.label));
+        Ok(())
+    }
+
+    /// Risks of both transaction parties on the `network`
+    pub fn analyze(
+        &self,
+        sender: &MultiId,
+        receiver: &MultiId,
+        network: &BlockchainNetwork,
+    ) -> Vec<AddressWarning> {
+        [
+            (ConfirmationRole::Sender, sender),
+            (ConfirmationRole::Receiver, receiver),
+        ]
+        .into_iter()
+        .flat_map(|(role, address)| {
+            self.address_risks(address, network)
+                .into_iter()
+                .map(move |risk| AddressWarning {
+                    role,
+                    address: address.clone(),
+                    risk,
+                })
+        })
+        .collect()
+    }
+
+    fn address_risks(&self, address: &MultiId, network: &BlockchainNetwork) -> Vec<AddressRisk> {
+        let mut risks = Vec::new();
+
+        match address {
+            // SS58 text keeps the network it was encoded for
+            VaneMultiAddress::Raw(raw) => {
+                let ss58 = std::str::from_utf8(raw)
+                    .ok()
+                    .and_then(|text| SpAccountId32::from_ss58check_with_version(text).ok());
+                if let Some((_, format)) = ss58 {
+                    let prefix = u16::from(format);
+                    // Moonbeam keeps an SS58 prefix but its accounts are 20 bytes
+                    if network.uses_evm_accounts() {
+                        risks.push(AddressRisk::NonEvmAddressOnEvmNetwork);
+                    } else if network.ss58_prefix() != Some(prefix) {
+                        risks.push(AddressRisk::ForeignSs58Prefix {
+                            prefix,
+                            network: BlockchainNetwork::from_ss58_prefix(prefix),
+                        });
+                    }
+                } else if let Err(err) = address.check_network(network) {
+                    risks.push(network_risk(err));
+                }
+            }
+            VaneMultiAddress::Address20(_) if !network.uses_evm_accounts() => {
+                risks.push(AddressRisk::EvmAddressOnNonEvmNetwork);
+            }
+            VaneMultiAddress::Id(_) | VaneMultiAddress::Address32(_)
+                if network.uses_evm_accounts() =>
+            {
+                risks.push(AddressRisk::NonEvmAddressOnEvmNetwork);
+            }
+            _ => {
+                if let Err(err) = address.check_network(network) {
+                    risks.push(network_risk(err));
+                }
+            }
+        }
+
+        if let Some((kind, label)) = self
+            .flagged
+            .get(network)
+            .and_then(|flagged| flagged.get(&canonical(address, network)))
+        {
+            risks.push(match kind {
+                FlaggedKind::Contract => AddressRisk::KnownContract(label.clone()),
+                FlaggedKind::Exchange => AddressRisk::ExchangeDeposit(label.clone()),
+            });
+        }
+        risks
+    }
+}
+
+/// Form in which `parse` gives back the address, so listed addresses are found whatever the variant
+fn canonical(address: &MultiId, network: &BlockchainNetwork) -> MultiId {
+    match address {
+        VaneMultiAddress::Id(account) => VaneMultiAddress::Address32(account.0),
+        VaneMultiAddress::Raw(raw) => std::str::from_utf8(raw)
+            .ok()
+            .and_then(|text| VaneMultiAddress::parse(text, network).ok())
+            .unwrap_or_else(|| address.clone()),
+        _ => address.clone(),
+    }
+}
+
+fn network_risk(err: AddressError) -> AddressRisk {
+    AddressRisk::InvalidForNetwork(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp_core::crypto::Ss58AddressFormat;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use subxt::utils::AccountId32;
+
+    const DEPOSIT_CONTRACT: &str = "0x00000000219ab540356cbb839cbe05303d7705fa";
+
+    fn ss58(account: [u8; 32], prefix: u16) -> String {
+        SpAccountId32::new(account).to_ss58check_with_version(Ss58AddressFormat::custom(prefix))
+    }
+
+    fn receiver_risks(
+        analyzer: &AddressRiskAnalyzer,
+        receiver: MultiId,
+        network: BlockchainNetwork,
+    ) -> Vec<AddressRisk> {
+        analyzer
+            .analyze(&receiver, &receiver, &network)
+            .into_iter()
+            .filter(|warning| warning.role == ConfirmationRole::Receiver)
+            .map(|warning| warning.risk)
+            .collect()
+    }
+
+    #[test]
+    fn flags_kusama_address_on_polkadot() {
+        let analyzer = AddressRiskAnalyzer::default();
+        let kusama = VaneMultiAddress::Raw(ss58([1; 32], 2).into_bytes());
+        assert_eq!(
+            receiver_risks(&analyzer, kusama.clone(), BlockchainNetwork::Polkadot),
+            vec![AddressRisk::ForeignSs58Prefix {
+                prefix: 2,
+                network: Some(BlockchainNetwork::Kusama),
+            }]
+        );
+        assert!(receiver_risks(&analyzer, kusama, BlockchainNetwork::Kusama).is_empty());
+
+        let polkadot = VaneMultiAddress::Raw(ss58([1; 32], 0).into_bytes());
+        assert!(receiver_risks(&analyzer, polkadot, BlockchainNetwork::Polkadot).is_empty());
+    }
+
+    #[test]
+    fn flags_evm_address_on_substrate_network() {
+        let analyzer = AddressRiskAnalyzer::default();
+        let evm = VaneMultiAddress::Address20([7; 20]);
+        assert_eq!(
+            receiver_risks(&analyzer, evm.clone(), BlockchainNetwork::Polkadot),
+            vec![AddressRisk::EvmAddressOnNonEvmNetwork]
+        );
+        assert_eq!(
+            receiver_risks(&analyzer, evm.clone(), BlockchainNetwork::Astar),
+            vec![AddressRisk::EvmAddressOnNonEvmNetwork]
+        );
+        assert!(receiver_risks(&analyzer, evm, BlockchainNetwork::Ethereum).is_empty());
+    }
+
+    #[test]
+    fn flags_substrate_account_on_moonbeam() {
+        let analyzer = AddressRiskAnalyzer::default();
+        for account in [
+            VaneMultiAddress::Id(AccountId32([1; 32])),
+            VaneMultiAddress::Address32([1; 32]),
+            VaneMultiAddress::Raw(ss58([1; 32], 1284).into_bytes()),
+            VaneMultiAddress::Raw(ss58([1; 32], 0).into_bytes()),
+        ] {
+            assert_eq!(
+                receiver_risks(&analyzer, account, BlockchainNetwork::Moonbeam),
+                vec![AddressRisk::NonEvmAddressOnEvmNetwork]
+            );
+        }
+        let evm = VaneMultiAddress::Address20([7; 20]);
+        assert!(receiver_risks(&analyzer, evm, BlockchainNetwork::Moonbeam).is_empty());
+    }
+
+    #[test]
+    fn flags_listed_contracts_and_exchanges() {
+        let mut analyzer = AddressRiskAnalyzer::default();
+        analyzer
+            .flag(FlaggedAddress {
+                network: BlockchainNetwork::Ethereum,
+                address: DEPOSIT_CONTRACT.to_string(),
+                kind: FlaggedKind::Contract,
+                label: "deposit contract".to_string(),
+            })
+            .unwrap();
+        analyzer
+            .flag(FlaggedAddress {
+                network: BlockchainNetwork::Polkadot,
+                address: ss58([9; 32], 0),
+                kind: FlaggedKind::Exchange,
+                label: "exchange".to_string(),
+            })
+            .unwrap();
+
+        let contract =
+            VaneMultiAddress::parse(DEPOSIT_CONTRACT, &BlockchainNetwork::Ethereum).unwrap();
+        assert_eq!(
+            receiver_risks(&analyzer, contract.clone(), BlockchainNetwork::Ethereum),
+            vec![AddressRisk::KnownContract("deposit contract".to_string())]
+        );
+        // listed on Ethereum only
+        assert!(receiver_risks(&analyzer, contract, BlockchainNetwork::Arbitrum).is_empty());
+        // found whatever the variant the address is given in
+        for exchange in [
+            VaneMultiAddress::Id(AccountId32([9; 32])),
+            VaneMultiAddress::Raw(ss58([9; 32], 0).into_bytes()),
+        ] {
+            assert_eq!(
+                receiver_risks(&analyzer, exchange, BlockchainNetwork::Polkadot),
+                vec![AddressRisk::ExchangeDeposit("exchange".to_string())]
+            );
+        }
+    }
+
+    #[test]
+    fn loads_the_flagged_addresses_list() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path =
+            std::env::temp_dir().join(format!("vane-flagged-{}-{nanos}.json", std::process::id()));
+        let fixture = format!(
+            r#"[
+                {{"network": "Ethereum", "address": "{DEPOSIT_CONTRACT}", "kind": "contract", "label": "deposit contract"}},
+                {{"network": "Polkadot", "address": "{}", "kind": "exchange", "label": "exchange"}}
+            ]"#,
+            ss58([9; 32], 0)
+        );
+        std::fs::write(&path, fixture).unwrap();
+        let analyzer = AddressRiskAnalyzer::load(&path);
+        std::fs::write(
+            &path,
+            r#"[{"network": "Polkadot", "address": "0x01", "kind": "exchange", "label": "bad"}]"#,
+        )
+        .unwrap();
+        let invalid = AddressRiskAnalyzer::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let analyzer = analyzer.unwrap();
+        assert_eq!(
+            receiver_risks(
+                &analyzer,
+                VaneMultiAddress::Address32([9; 32]),
+                BlockchainNetwork::Polkadot
+            ),
+            vec![AddressRisk::ExchangeDeposit("exchange".to_string())]
+        );
+        assert!(invalid.is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use primitives::{BlockchainNetwork, VaneMultiAddress};
+use sp_core::ed25519::{Public as ed25519Public, Signature as Ed25519Signature};
+use sp_runtime::traits::Verify;
+use subxt::utils::AccountId32;
+
+// ============================================================================
+// Solana ( ed25519 ) confirmation signatures
+
+/// Raw 32 bytes pubkey of a Solana account
+/// `Raw` addresses other than the raw pubkey bytes are expected to carry the base58 pubkey string
+pub fn public_key(address: &VaneMultiAddress<AccountId32, ()>) -> anyhow::Result<[u8; 32]> {
+    match address {
+        VaneMultiAddress::Raw(raw) if raw.len() != 32 => {
+            let pubkey = std::str::from_utf8(raw)
+                .map_err(|_| anyhow::anyhow!("Solana pubkey is not a base58 string"))?;
+            let address = VaneMultiAddress::parse(pubkey, &BlockchainNetwork::Solana)?;
+            Ok(address.ed25519_public()?)
+        }
+        _ => Ok(address.ed25519_public()?),
+    }
+}
+
+/// Byte renderings of `message` a Solana wallet may have signed
+/// Phantom `signMessage` signs the bytes handed by the dapp as they are, and since binary
+/// data is unreadable in the wallet prompt dapps hand the `0x` hex text of the message instead
+fn sign_message_framings(message: &[u8]) -> [Vec<u8>; 2] {
+    [
+        message.to_vec(),
+        sp_core::bytes::to_hex(message, false).into_bytes(),
+    ]
+}
+
+/// Whether `signature` over `message` was produced by the ed25519 `pubkey`
+pub fn verify(message: &[u8], signature: &[u8], pubkey: &[u8; 32]) -> anyhow::Result<bool> {
+    let sig = Ed25519Signature::from_slice(signature)
+        .ok_or(anyhow::anyhow!("Failed to convert signature ed25519"))?;
+    let public = ed25519Public::from_raw(*pubkey);
+
+    Ok(sign_message_framings(message)
+        .iter()
+        .any(|framed| sig.verify(&framed[..], &public)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 8032 ed25519 test 2
+    const PUBKEY: &str = "0x3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c";
+    const PUBKEY_BASE58: &str = "586Z7H2vpX9qNhN2T4e9Utugie3ogjbxzGaMtM3E6HR5";
+    const MESSAGE: &[u8] = &[0x72];
+    // signature over `MESSAGE` as is
+    const RAW_SIGNATURE: &str = "0x92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00";
+    // signature over the `0x72` hex text of `MESSAGE`
+    const HEX_SIGNATURE: &str = "0x9eefae2691f95c64913de997c1a1632668f2c8f307deccab325df2ca7e58599fa27c5eea53bb90f6cfbd94aaf1b5779cc95271416d2cd855befaac7ae7c43c00";
+    // RFC 8032 ed25519 test 1
+    const OTHER_PUBKEY: &str = "0xd75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+
+    fn hex(value: &str) -> Vec<u8> {
+        sp_core::bytes::from_hex(value).unwrap()
+    }
+
+    fn pubkey(value: &str) -> [u8; 32] {
+        hex(value).try_into().unwrap()
+    }
+
+    #[test]
+    fn public_key_of_base58_and_raw_addresses() {
+        let base58 = VaneMultiAddress::Raw(PUBKEY_BASE58.as_bytes().to_vec());
+        assert_eq!(public_key(&base58).unwrap(), pubkey(PUBKEY));
+
+        let raw = VaneMultiAddress::Raw(hex(PUBKEY));
+        assert_eq!(public_key(&raw).unwrap(), pubkey(PUBKEY));
+
+        let address32 = VaneMultiAddress::Address32(pubkey(PUBKEY));
+        assert_eq!(public_key(&address32).unwrap(), pubkey(PUBKEY));
+
+        let invalid = VaneMultiAddress::Raw(b"0OIl-not-base58".to_vec());
+        assert!(public_key(&invalid).is_err());
+    }
+
+    #[test]
+    fn verifies_raw_and_hex_framed_signatures() {
+        assert!(verify(MESSAGE, &hex(RAW_SIGNATURE), &pubkey(PUBKEY)).unwrap());
+        assert!(verify(MESSAGE, &hex(HEX_SIGNATURE), &pubkey(PUBKEY)).unwrap());
+    }
+
+    #[test]
+    fn rejects_wrong_pubkey_and_message() {
+        assert!(!verify(MESSAGE, &hex(RAW_SIGNATURE), &pubkey(OTHER_PUBKEY)).unwrap());
+        assert!(!verify(MESSAGE, &hex(HEX_SIGNATURE), &pubkey(OTHER_PUBKEY)).unwrap());
+        assert!(!verify(&[0x73], &hex(RAW_SIGNATURE), &pubkey(PUBKEY)).unwrap());
+        assert!(verify(MESSAGE, &hex(RAW_SIGNATURE)[..63], &pubkey(PUBKEY)).is_err());
+    }
+}
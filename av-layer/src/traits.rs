@@ -1,39 +1,51 @@
+use crate::handlers::{MultiId, TxKey};
 use jsonrpsee::core::SubscriptionResult;
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
 use primitives::*;
-use subxt::utils::{AccountId32, MultiAddress, MultiSignature};
+use serde_json::Value as JsonValue;
+use subxt::utils::AccountId32;
 /// Submssion of the transaction object
 /// Handling confirmation of transaction from receiver and sender
 /// A websocket connection
 #[rpc(server, client)]
 pub trait Transaction {
-    /// Takes in transaction function `call`, `sender address` and `receiver address`
+    /// Takes in transaction function `call`, `sender address`, `receiver address` and the target `network`
     /// A transaction object will be built based on the params and the object will be subjected for confirmation
+    /// `Raw` address text ( SS58, hex ) is stored as the account it names
+    /// returns the `tx_id` and `multi_id` identifying the transaction
     #[method(name = "submitTransaction")]
     async fn submit_transaction(
         &self,
         call_data: VaneCallData,
         sender: VaneMultiAddress<AccountId32, ()>,
         receiver: VaneMultiAddress<AccountId32, ()>,
-    ) -> RpcResult<()>;
+        network: BlockchainNetwork,
+    ) -> RpcResult<TxSubmissionObject>;
 
+    /// Lifecycle view `Vec<TxStateObject>` of the transactions sent by `sender`
+    /// narrowed down to a single transaction when `tx_id` is provided
+    /// `encoding` selects between hex encoded SCALE bytes ( default ) and JSON
     #[method(name = "getTransaction")]
     async fn get_transaction(
         &self,
         sender: VaneMultiAddress<AccountId32, ()>,
-        tx_id: Option<Vec<u8>>,
-    ) -> RpcResult<Vec<u8>>;
+        tx_id: Option<TxId>,
+        encoding: Option<ResponseEncoding>,
+    ) -> RpcResult<JsonValue>;
 
     /// Subscription to start listening to any upcoming confirmation request
-    /// returns `Vec<TxConfirmationObject>` in encoded format
-    #[subscription(name = "subscribeTxConfirmation", item=Vec<Vec<u8>>)]
+    /// returns the pending `TxObject`s first as `SubscriptionUpdate::Snapshot` and then a `SubscriptionUpdate::Event`
+    /// for every change on the address transactions as long as the subscription lives
+    #[subscription(name = "subscribeTxConfirmation", item=SubscriptionUpdate<TxObject>)]
     async fn subscribe_tx_confirmation(
         &self,
         address: VaneMultiAddress<AccountId32, ()>,
     ) -> SubscriptionResult;
 
     /// Subscriptiom for sender to listen to incoming confirmed transactions from the receiver
-    #[subscription(name = "subscribeTxConfirmationSender", item=Vec<TxConfirmationObject>)]
+    /// returns the `TxConfirmationObject`s first as `SubscriptionUpdate::Snapshot` and then a `SubscriptionUpdate::Event`
+    /// for every change on the address transactions as long as the subscription lives
+    #[subscription(name = "subscribeTxConfirmationSender", item=SubscriptionUpdate<TxConfirmationObject>)]
     async fn subscribe_tx_confirmation_sender(
         &self,
         address: VaneMultiAddress<AccountId32, ()>,
@@ -45,6 +57,7 @@ pub trait Transaction {
         &self,
         address: VaneMultiAddress<AccountId32, ()>,
         multi_id: VaneMultiAddress<AccountId32, ()>,
+        tx_id: TxId,
         signature: Vec<u8>,
         network: BlockchainNetwork,
     ) -> RpcResult<()>;
@@ -56,25 +69,157 @@ pub trait Transaction {
         &self,
         address: VaneMultiAddress<AccountId32, ()>,
         multi_id: VaneMultiAddress<AccountId32, ()>,
+        tx_id: TxId,
         signature: Vec<u8>,
         network: BlockchainNetwork,
     ) -> RpcResult<()>;
 
     /// Revert transaction in address verification layer
+    /// `signature` is the sender signature over `ConfirmationPayload::revert` of the transaction
+    /// only accepted until both parties confirmed the transaction, a transaction locked by failed confirmations included
     #[method(name = "senderRevert")]
     async fn sender_revert_transaction(
         &self,
         address: VaneMultiAddress<AccountId32, ()>,
         multi_id: VaneMultiAddress<AccountId32, ()>,
+        tx_id: TxId,
+        signature: Vec<u8>,
         network: BlockchainNetwork,
     ) -> RpcResult<()>;
 
-    /// Handling confirmation of transaction from receiver and sender
-    /// A websocket connection
+    /// Receivers `sender` completed the confirmation flow with, narrowed down to `network` when provided
+    /// returns `Vec<KnownCounterparty>`
+    #[method(name = "vane_knownCounterparties")]
+    async fn known_counterparties(
+        &self,
+        sender: VaneMultiAddress<AccountId32, ()>,
+        network: Option<BlockchainNetwork>,
+    ) -> RpcResult<Vec<KnownCounterparty>>;
+}
 
+/// Methods of the network simulation and router layer
+/// only served on the router endpoint, which refuses connections without the router secret
+#[rpc(server, client)]
+pub trait Router {
     /// This should be a websocket connection to network router server
     /// handling propagating to be simulated and
     /// account control attestation after txn execution ( i.e depositing to the specified acount)
-    #[subscription(name = "receiveConfirmedTx", unsubscribe = "unsubReceiveConfirmedTx", item=TxSimulationObject)]
+    /// Each `TxSimulationDelivery` must be acknowledged with `ackConfirmedTx`
+    /// otherwise it is delivered again once its lease expires
+    #[subscription(name = "receiveConfirmedTx", unsubscribe = "unsubReceiveConfirmedTx", item=TxSimulationDelivery)]
     async fn receive_confirmed_tx(&self) -> SubscriptionResult;
+
+    /// Acknowledge a `TxSimulationDelivery` handled by the network simulation and router layer
+    #[method(name = "ackConfirmedTx")]
+    async fn ack_confirmed_tx(&self, delivery_id: u64) -> RpcResult<()>;
+
+    /// Progress of a confirmed transaction on its network reported by the network router layer
+    /// forwarded to the sender and receiver subscriptions as `TxEvent::Executed`
+    #[method(name = "reportTxStatus")]
+    async fn report_tx_status(&self, tx_id: TxId, status: TxExecutionStatus) -> RpcResult<()>;
+}
+
+/// Write of a `TxStorage::apply` batch
+pub enum StorageWrite {
+    /// Store encoded `TxObject` under its `TxKey`
+    SetTransaction(TxKey, Vec<u8>),
+    /// Store the `TxKey` of every pending transaction of an account
+    SetMultiIds(MultiId, Vec<TxKey>),
+    /// Store encoded `TxConfirmationObject` under its `TxKey`
+    SetConfirmation(TxKey, Vec<u8>),
+    /// Append encoded `TxSimulationObject` to the back of the simulation queue
+    PushSimulation(Vec<u8>),
+    /// Index a transaction in its confirmation phase by the time its confirmation window passes
+    IndexExpiry(u64, TxKey),
+    /// Drop the expiry index entry of a transaction which left its confirmation phase
+    UnindexExpiry(u64, TxKey),
+}
+
+/// Storage backend for the address verification layer
+/// Values are stored SCALE encoded and decoding is left to `TransactionHandler`
+/// so that every backend stays agnostic of the transaction objects layout
+pub trait TxStorage: Send {
+    // ============================================================================
+    // DB_DATA
+
+    /// Store encoded `TxObject` under its `TxKey`, the key is indexed by its `tx_id`
+    fn set_transaction(&mut self, tx_key: TxKey, tx: Vec<u8>) -> anyhow::Result<()>;
+
+    fn get_transaction(&self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>>;
+
+    fn remove_transaction(&mut self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// `TxKey` of the stored transaction with the `tx_id`
+    fn find_tx_key(&self, tx_id: &TxId) -> anyhow::Result<Option<TxKey>>;
+
+    /// Expiry index entries due at `now`, soonest first
+    fn expiring_transactions(&self, now: u64) -> anyhow::Result<Vec<(u64, TxKey)>>;
+
+    /// Apply every write of the batch or none of them, even across a crash
+    fn apply(&mut self, writes: Vec<StorageWrite>) -> anyhow::Result<()>;
+
+    /// Store the `TxKey` of every pending transaction of an account ( sender | receiver )
+    fn set_multi_ids(&mut self, account: MultiId, multi_ids: Vec<TxKey>) -> anyhow::Result<()>;
+
+    fn get_multi_ids(&self, account: &MultiId) -> anyhow::Result<Option<Vec<TxKey>>>;
+
+    /// Store encoded `TxConfirmationObject` under its `TxKey`
+    fn set_confirmation(&mut self, tx_key: TxKey, tx_confirmation: Vec<u8>) -> anyhow::Result<()>;
+
+    fn get_confirmation(&self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>>;
+
+    fn remove_confirmation(&mut self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Append encoded `TxSimulationObject` to the back of the simulation queue
+    fn push_simulation(&mut self, tx_simulation: Vec<u8>) -> anyhow::Result<()>;
+
+    /// Hand the oldest encoded `TxSimulationObject` not leased to any consumer ( or whose lease expired )
+    /// leasing it for `lease` millis from `now`, returns the entry delivery id with the object
+    fn lease_simulation(&mut self, now: u64, lease: u64) -> anyhow::Result<Option<(u64, Vec<u8>)>>;
+
+    /// Drop the acknowledged entry from the simulation queue
+    /// returns `false` if the entry was already acknowledged
+    fn ack_simulation(&mut self, id: u64) -> anyhow::Result<bool>;
+
+    fn is_simulation_queued(&self, id: u64) -> anyhow::Result<bool>;
+
+    #[cfg(test)]
+    fn simulation_len(&self) -> anyhow::Result<usize>;
+
+    /// Whether any queued encoded `TxSimulationObject` matches `find`
+    fn contains_simulation(&self, find: &dyn Fn(&[u8]) -> bool) -> anyhow::Result<bool>;
+
+    /// Drop every queued encoded `TxSimulationObject` matching `remove`
+    /// returns the number of dropped entries
+    fn remove_simulation(&mut self, remove: &dyn Fn(&[u8]) -> bool) -> anyhow::Result<usize>;
+
+    /// Keep the encoded `TxObject` of a transaction reverted by `sender`
+    fn record_reverted(&mut self, sender: MultiId, tx: Vec<u8>) -> anyhow::Result<()>;
+
+    fn get_reverted(&self, sender: &MultiId) -> anyhow::Result<Vec<Vec<u8>>>;
+
+    /// Count a confirmation of the transaction which failed signature verification
+    /// returns the number of failed confirmations so far
+    fn record_failed_confirmation(&mut self, tx_key: &TxKey) -> anyhow::Result<u32>;
+
+    fn get_failed_confirmations(&self, tx_key: &TxKey) -> anyhow::Result<u32>;
+
+    /// Store the latest encoded `TxExecutionStatus` reported by the router
+    fn set_execution_status(&mut self, tx_key: TxKey, status: Vec<u8>) -> anyhow::Result<()>;
+
+    fn get_execution_status(&self, tx_key: &TxKey) -> anyhow::Result<Option<Vec<u8>>>;
+
+    /// Store the encoded `KnownCounterparty` entries of `sender`
+    fn set_counterparties(
+        &mut self,
+        sender: MultiId,
+        counterparties: Vec<Vec<u8>>,
+    ) -> anyhow::Result<()>;
+
+    fn get_counterparties(&self, sender: &MultiId) -> anyhow::Result<Vec<Vec<u8>>>;
+
+    // ============================================================================
+    // METRICS
+
+    fn record_subscriber(&mut self, id: JsonValue) -> anyhow::Result<()>;
 }
@@ -0,0 +1,349 @@
+use crate::handlers::MultiId;
+use crate::{evm, solana};
+use parity_scale_codec::Decode;
+use primitives::{AddressError, BlockchainNetwork, ConfirmationPayload};
+use sp_core::sr25519::{Public as sr25519Public, Signature as Sr25519Signature};
+use sp_runtime::traits::Verify;
+use sp_runtime::MultiSignature;
+use std::{collections::HashMap, fmt, sync::Arc};
+
+/// Reasons a confirmation signature could not be checked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationError {
+    /// No verifier is registered for the network
+    UnsupportedNetwork(BlockchainNetwork),
+    /// The address can not be turned into a public key of the scheme
+    InvalidAddress(String),
+    /// The signature bytes are malformed for the scheme
+    MalformedSignature(String),
+    /// The signature is well formed but not produced by the address over the payload
+    SignatureMismatch,
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationError::UnsupportedNetwork(network) => {
+                write!(f, "Blockchain network not supported: {network:?}")
+            }
+            VerificationError::InvalidAddress(reason) => write!(f, "Invalid address: {reason}"),
+            VerificationError::MalformedSignature(reason) => {
+                write!(f, "Malformed signature: {reason}")
+            }
+            VerificationError::SignatureMismatch => {
+                write!(f, "Signature does not match the address")
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+/// Signature scheme used to check confirmations on a network
+/// returns `Ok(false)` for a well formed signature not produced by `address`
+pub trait ConfirmationVerifier: Send + Sync {
+    fn verify(
+        &self,
+        address: &MultiId,
+        msg: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, VerificationError>;
+
+    /// Whether `signature` signs the confirmation `payload`, any of its renderings by default
+    fn verify_payload(
+        &self,
+        address: &MultiId,
+        payload: &ConfirmationPayload,
+        signature: &[u8],
+    ) -> Result<bool, VerificationError> {
+        for msg in payload.renderings() {
+            if self.verify(address, &msg, signature)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl From<AddressError> for VerificationError {
+    fn from(err: AddressError) -> Self {
+        VerificationError::InvalidAddress(err.to_string())
+    }
+}
+
+// ============================================================================
+// VERIFIERS
+
+/// Raw 64 bytes sr25519 signatures of Substrate accounts
+pub struct Sr25519Verifier;
+
+impl ConfirmationVerifier for Sr25519Verifier {
+    fn verify(
+        &self,
+        address: &MultiId,
+        msg: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, VerificationError> {
+        let sig = Sr25519Signature::from_slice(signature).ok_or(
+            VerificationError::MalformedSignature("expected 64 bytes sr25519".to_string()),
+        )?;
+        let public = sr25519Public::from_raw(address.sr25519_public()?);
+        Ok(sig.verify(msg, &public))
+    }
+}
+
+/// SCALE encoded Substrate `MultiSignature` ( sr25519 | ed25519 | ecdsa ) of Substrate accounts
+pub struct MultiSignatureVerifier;
+
+impl ConfirmationVerifier for MultiSignatureVerifier {
+    fn verify(
+        &self,
+        address: &MultiId,
+        msg: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, VerificationError> {
+        let sig = MultiSignature::decode(&mut &signature[..])
+            .map_err(|err| VerificationError::MalformedSignature(err.to_string()))?;
+        let account = sp_runtime::AccountId32::new(address.account_id_32()?);
+        Ok(sig.verify(msg, &account))
+    }
+}
+
+/// ed25519 signatures of Solana pubkeys, see `solana::verify` for the accepted framings
+pub struct Ed25519Verifier;
+
+impl ConfirmationVerifier for Ed25519Verifier {
+    fn verify(
+        &self,
+        address: &MultiId,
+        msg: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, VerificationError> {
+        let pubkey = solana::public_key(address)
+            .map_err(|err| VerificationError::InvalidAddress(err.to_string()))?;
+        solana::verify(msg, signature, &pubkey)
+            .map_err(|err| VerificationError::MalformedSignature(err.to_string()))
+    }
+}
+
+/// secp256k1 `personal_sign` and EIP-712 signatures of 20 bytes EVM addresses
+pub struct EcdsaVerifier;
+
+impl ConfirmationVerifier for EcdsaVerifier {
+    fn verify(
+        &self,
+        address: &MultiId,
+        msg: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, VerificationError> {
+        let account = address.evm_address()?;
+        evm::verify_personal_sign(msg, signature, &account)
+            .map_err(|err| VerificationError::MalformedSignature(err.to_string()))
+    }
+
+    /// `personal_sign` of any rendering or EIP-712 signature of the payload typed struct
+    fn verify_payload(
+        &self,
+        address: &MultiId,
+        payload: &ConfirmationPayload,
+        signature: &[u8],
+    ) -> Result<bool, VerificationError> {
+        for msg in payload.renderings() {
+            if self.verify(address, &msg, signature)? {
+                return Ok(true);
+            }
+        }
+        let account = address.evm_address()?;
+        evm::verify_typed_data(payload, signature, &account)
+            .map_err(|err| VerificationError::MalformedSignature(err.to_string()))
+    }
+}
+
+// ============================================================================
+// REGISTRY
+
+/// Confirmation verifiers of every supported network
+/// verifiers of a network are tried in registration order, the first one is the primary scheme
+#[derive(Clone)]
+pub struct VerifierRegistry {
+    verifiers: HashMap<BlockchainNetwork, Vec<Arc<dyn ConfirmationVerifier>>>,
+}
+
+impl Default for VerifierRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            verifiers: HashMap::new(),
+        };
+        registry.register(BlockchainNetwork::Polkadot, Sr25519Verifier);
+        registry.register(BlockchainNetwork::Kusama, Sr25519Verifier);
+        // ed25519 and ecdsa Substrate accounts sign `MultiSignature`s
+        registry.register_fallback(BlockchainNetwork::Polkadot, MultiSignatureVerifier);
+        registry.register_fallback(BlockchainNetwork::Kusama, MultiSignatureVerifier);
+        registry.register(BlockchainNetwork::Astar, MultiSignatureVerifier);
+        registry.register(BlockchainNetwork::Solana, Ed25519Verifier);
+        for network in [
+            BlockchainNetwork::Moonbeam,
+            BlockchainNetwork::Ethereum,
+            BlockchainNetwork::Optimism,
+            BlockchainNetwork::Arbitrum,
+        ] {
+            registry.register(network, EcdsaVerifier);
+        }
+        registry
+    }
+}
+
+impl VerifierRegistry {
+    /// Set the verifier of `network`, replacing the previous ones
+    pub fn register(
+        &mut self,
+        network: BlockchainNetwork,
+        verifier: impl ConfirmationVerifier + 'static,
+    ) {
+        self.verifiers.insert(network, vec![Arc::new(verifier)]);
+    }
+
+    /// Add a verifier tried on `network` when the previous ones do not accept the signature
+    pub fn register_fallback(
+        &mut self,
+        network: BlockchainNetwork,
+        verifier: impl ConfirmationVerifier + 'static,
+    ) {
+        self.verifiers
+            .entry(network)
+            .or_default()
+            .push(Arc::new(verifier));
+    }
+
+    /// Check `signature` of `address` signs any rendering of the confirmation `payload`
+    /// with any scheme of the payload network
+    /// a signature no scheme can read is reported with the error of the primary scheme
+    pub fn verify_payload(
+        &self,
+        address: &MultiId,
+        payload: &ConfirmationPayload,
+        signature: &[u8],
+    ) -> Result<(), VerificationError> {
+        let verifiers = self
+            .verifiers
+            .get(&payload.network)
+            .filter(|verifiers| !verifiers.is_empty())
+            .ok_or(VerificationError::UnsupportedNetwork(
+                payload.network.clone(),
+            ))?;
+        let mut first_err = None;
+        let mut well_formed = false;
+        for verifier in verifiers {
+            match verifier.verify_payload(address, payload, signature) {
+                Ok(true) => return Ok(()),
+                Ok(false) => well_formed = true,
+                Err(err) => {
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+        match first_err {
+            Some(err) if !well_formed => Err(err),
+            _ => Err(VerificationError::SignatureMismatch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_scale_codec::Encode;
+    use primitives::{ConfirmationRole, TxObject, VaneCallData, VaneMultiAddress};
+    use sp_core::{ed25519, sr25519, Pair};
+    use subxt::utils::{AccountId32, MultiAddress};
+
+    fn payload(sender: [u8; 32], network: BlockchainNetwork) -> ConfirmationPayload {
+        let tx = TxObject::new(
+            VaneCallData::new(network.clone(), 10),
+            MultiAddress::Id(AccountId32(sender)),
+            MultiAddress::Id(AccountId32([2; 32])),
+            network,
+        );
+        ConfirmationPayload::new(&tx, ConfirmationRole::Sender)
+    }
+
+    fn account(public: [u8; 32]) -> MultiId {
+        VaneMultiAddress::Id(AccountId32(public))
+    }
+
+    #[test]
+    fn dispatches_on_the_payload_network() {
+        let registry = VerifierRegistry::default();
+        let pair = sr25519::Pair::from_seed(&[1; 32]);
+        let polkadot = payload(pair.public().0, BlockchainNetwork::Polkadot);
+        let signature = pair.sign(&polkadot.encode()).0;
+
+        assert_eq!(
+            registry.verify_payload(&account(pair.public().0), &polkadot, &signature),
+            Ok(())
+        );
+        // the same account and signature on Solana is checked as ed25519
+        let solana = payload(pair.public().0, BlockchainNetwork::Solana);
+        assert!(registry
+            .verify_payload(&account(pair.public().0), &solana, &signature)
+            .is_err());
+    }
+
+    #[test]
+    fn substrate_networks_fall_back_to_multi_signature() {
+        let registry = VerifierRegistry::default();
+        let sr25519 = sr25519::Pair::from_seed(&[1; 32]);
+        let ed25519 = ed25519::Pair::from_seed(&[2; 32]);
+
+        for network in [BlockchainNetwork::Polkadot, BlockchainNetwork::Kusama] {
+            let payload = payload(sr25519.public().0, network.clone());
+            let signature = MultiSignature::from(sr25519.sign(&payload.encode())).encode();
+            assert_eq!(
+                registry.verify_payload(&account(sr25519.public().0), &payload, &signature),
+                Ok(())
+            );
+
+            let payload = self::payload(ed25519.public().0, network);
+            let signature = MultiSignature::from(ed25519.sign(&payload.encode())).encode();
+            assert_eq!(
+                registry.verify_payload(&account(ed25519.public().0), &payload, &signature),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn reports_unsupported_network() {
+        let registry = VerifierRegistry {
+            verifiers: HashMap::new(),
+        };
+        let payload = payload([1; 32], BlockchainNetwork::Polkadot);
+
+        assert_eq!(
+            registry.verify_payload(&account([1; 32]), &payload, &[0; 64]),
+            Err(VerificationError::UnsupportedNetwork(
+                BlockchainNetwork::Polkadot
+            ))
+        );
+    }
+
+    #[test]
+    fn reports_malformed_and_mismatched_signatures() {
+        let registry = VerifierRegistry::default();
+        let pair = sr25519::Pair::from_seed(&[1; 32]);
+        let other = sr25519::Pair::from_seed(&[3; 32]);
+        let payload = payload(pair.public().0, BlockchainNetwork::Polkadot);
+
+        // no scheme of the network reads 10 bytes
+        assert!(matches!(
+            registry.verify_payload(&account(pair.public().0), &payload, &[0; 10]),
+            Err(VerificationError::MalformedSignature(_))
+        ));
+        // well formed but produced by another key
+        let signature = other.sign(&payload.encode()).0;
+        assert_eq!(
+            registry.verify_payload(&account(pair.public().0), &payload, &signature),
+            Err(VerificationError::SignatureMismatch)
+        );
+    }
+}
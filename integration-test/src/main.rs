@@ -12,21 +12,11 @@
 //! 7. Sender confirmation should change the state of the transaction to accepted and ready to be propagated to network router layer
 //!
 
-use jsonrpsee::core::client::{ClientT, SubscriptionClientT};
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::rpc_params;
 use jsonrpsee::ws_client::{WsClient, WsClientBuilder};
-use primitives::{
-    BlockchainNetwork, TxConfirmationObject, TxObject, VaneCallData, VaneMultiAddress,
-};
-use sp_core::ecdsa::{Public as ecdsaPublic, Signature as ECDSASignature};
-use sp_core::ed25519::{Public as ed25519Public, Signature as Ed25519Signature};
-use sp_core::sr25519::{
-    Pair as sr25519Pair, Public as sr25519Public, Signature as Sr25519Signature,
-};
-use sp_core::{Pair, H256};
-use sp_runtime::traits::{IdentifyAccount, Verify};
-use subxt::tx::TxPayload;
+use primitives::{BlockchainNetwork, TxSubmissionObject, VaneCallData, VaneMultiAddress};
 use subxt::utils::{AccountId32, MultiAddress};
-use subxt::{Metadata, OnlineClient, PolkadotConfig};
 use subxt_signer::sr25519::{dev, Keypair};
 #[subxt::subxt(runtime_metadata_path = "polkadot.scale")]
 pub mod polkadot {}
@@ -40,7 +30,7 @@ async fn main() -> anyhow::Result<()> {
     // generate accounts
     // let alicePair = sr25519Pair::from_string("//Alice", None).expect("Failed to generate key pair");
     // let bobPair = sr25519Pair::from_string("//Bob", None).expect("Failed to generate key pair");
-    let alice = dev::alice().public_key();
+    let _alice = dev::alice().public_key();
     //let bob:VaneMultiAddress<u128,u32> = VaneMultiAddress::Address32(dev::bob().public_key().into());
     // construct a transfer tx
     //let transfer_call = polkadot::tx().balances().transfer_keep_alive(bob, 10_000);
@@ -78,7 +68,7 @@ impl PolkadotTest {
         sender: Keypair,
         receiver: Keypair,
         amount: u128,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<Option<TxSubmissionObject>> {
         // build a transfer keep alive polkadot call
         let sender_multi: VaneMultiAddress<AccountId32, ()> =
             VaneMultiAddress::Address32(sender.public_key().0);
@@ -88,29 +78,36 @@ impl PolkadotTest {
         let vane_call_data = VaneCallData::new(BlockchainNetwork::Polkadot, amount);
         // use the client to submit the transaction to av layer
         if self.client.is_connected() {
-            self.client
+            let submitted: TxSubmissionObject = self
+                .client
                 .request(
                     "submitTransaction",
-                    vec![sender_multi,receiver_multi],
+                    rpc_params![
+                        vane_call_data,
+                        sender_multi,
+                        receiver_multi,
+                        BlockchainNetwork::Polkadot
+                    ],
                 )
                 .await?;
+            return Ok(Some(submitted));
         }
-        Ok(())
+        Ok(None)
     }
 
-    pub async fn listen_to_incoming_tx(address: MultiAddress<u128, ()>) -> anyhow::Result<()> {
+    pub async fn listen_to_incoming_tx(_address: MultiAddress<u128, ()>) -> anyhow::Result<()> {
         todo!()
     }
 
-    pub async fn sender_listen_confirmed_tx(sender: MultiAddress<u128, ()>) -> anyhow::Result<()> {
+    pub async fn sender_listen_confirmed_tx(_sender: MultiAddress<u128, ()>) -> anyhow::Result<()> {
         todo!()
     }
 
-    pub async fn receiver_tx_confirm(receiver_pair: Keypair) -> anyhow::Result<()> {
+    pub async fn receiver_tx_confirm(_receiver_pair: Keypair) -> anyhow::Result<()> {
         todo!()
     }
 
-    pub async fn sender_tx_confirm(sender_pair: Keypair) -> anyhow::Result<()> {
+    pub async fn sender_tx_confirm(_sender_pair: Keypair) -> anyhow::Result<()> {
         todo!()
     }
 
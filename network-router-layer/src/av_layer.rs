@@ -0,0 +1,68 @@
+use crate::traits::{AvLayerApi, AvLayerClient};
+use futures::stream::{BoxStream, StreamExt};
+use jsonrpsee::ws_client::{HeaderMap, HeaderValue, WsClient, WsClientBuilder};
+use primitives::{TxExecutionStatus, TxId, TxSimulationDelivery};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Websocket connection to the av-layer router endpoint, authenticated with the router secret
+/// a lost connection is replaced by a new one on the next call
+pub struct AvLayerConnection {
+    url: String,
+    headers: HeaderMap,
+    client: Mutex<Arc<WsClient>>,
+}
+
+impl AvLayerConnection {
+    pub async fn connect(url: impl Into<String>, secret: &str) -> anyhow::Result<Self> {
+        let url = url.into();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            HeaderValue::from_str(&format!("Bearer {secret}"))?,
+        );
+        let client = Self::build(&url, &headers).await?;
+        Ok(Self {
+            url,
+            headers,
+            client: Mutex::new(Arc::new(client)),
+        })
+    }
+
+    async fn build(url: &str, headers: &HeaderMap) -> anyhow::Result<WsClient> {
+        Ok(WsClientBuilder::default()
+            .set_headers(headers.clone())
+            .build(url)
+            .await?)
+    }
+
+    /// Connected client, reconnecting first when the previous connection was lost
+    async fn client(&self) -> anyhow::Result<Arc<WsClient>> {
+        let mut client = self.client.lock().await;
+        if !client.is_connected() {
+            tracing::warn!("connection to av-layer lost, reconnecting to {}", self.url);
+            *client = Arc::new(Self::build(&self.url, &self.headers).await?);
+        }
+        Ok(client.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl AvLayerApi for AvLayerConnection {
+    async fn confirmed_txs(
+        &self,
+    ) -> anyhow::Result<BoxStream<'static, anyhow::Result<TxSimulationDelivery>>> {
+        let deliveries = self.client().await?.receive_confirmed_tx().await?;
+        Ok(deliveries
+            .map(|delivery| delivery.map_err(anyhow::Error::from))
+            .boxed())
+    }
+
+    async fn ack_confirmed_tx(&self, delivery_id: u64) -> anyhow::Result<()> {
+        Ok(AvLayerClient::ack_confirmed_tx(self.client().await?.as_ref(), delivery_id).await?)
+    }
+
+    async fn report_tx_status(&self, tx_id: TxId, status: TxExecutionStatus) -> anyhow::Result<()> {
+        Ok(AvLayerClient::report_tx_status(self.client().await?.as_ref(), tx_id, status).await?)
+    }
+}
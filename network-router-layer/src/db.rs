@@ -0,0 +1,230 @@
+use crate::traits::Submission;
+use parity_scale_codec::{Decode, Encode};
+use primitives::{TxExecutionStatus, TxId};
+use sled::transaction::{TransactionResult, Transactional};
+use std::path::Path;
+
+/// Embedded on-disk database keeping the latest `TxExecutionStatus` of every routed transaction
+/// so a transaction delivered again after a restart is never submitted twice
+pub struct StatusDB {
+    db: sled::Db,
+    // `tx_id` ===> encoded `TxExecutionStatus`
+    statuses: sled::Tree,
+    // `tx_id` ===> encoded ( `TxId`, `TxExecutionStatus` ) the av-layer did not accept yet
+    unreported: sled::Tree,
+    // `tx_id` ===> big endian `u64` id of the run submitting the transaction
+    claims: sled::Tree,
+    // `tx_id` ===> encoded `Submission` last signed for the transaction
+    submissions: sled::Tree,
+    // Id of this run of the router, unique across restarts
+    run_id: u64,
+}
+
+impl StatusDB {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::with_db(sled::open(path)?)
+    }
+
+    pub(crate) fn with_db(db: sled::Db) -> anyhow::Result<Self> {
+        Ok(Self {
+            statuses: db.open_tree("statuses")?,
+            unreported: db.open_tree("unreported")?,
+            claims: db.open_tree("claims")?,
+            submissions: db.open_tree("submissions")?,
+            run_id: db.generate_id()?,
+            db,
+        })
+    }
+
+    /// Claim the submission of `tx_id` for this run
+    /// returns `false` while another delivery of this run is submitting the transaction
+    /// a claim left by a previous run is taken over as that run stopped
+    /// the `Submission` it recorded tells whether it got to submit the transaction
+    pub fn claim(&self, tx_id: &TxId) -> anyhow::Result<bool> {
+        let run_id = self.run_id.to_be_bytes();
+        let mut current = None;
+        loop {
+            match self
+                .claims
+                .compare_and_swap(tx_id.as_str(), current, Some(&run_id[..]))?
+            {
+                Ok(()) => {
+                    self.db.flush()?;
+                    return Ok(true);
+                }
+                Err(err) if err.current.as_deref() == Some(&run_id[..]) => return Ok(false),
+                Err(err) => current = err.current,
+            }
+        }
+    }
+
+    /// Give up the claim of this run on `tx_id` so a later delivery submits it
+    pub fn release(&self, tx_id: &TxId) -> anyhow::Result<()> {
+        let run_id = self.run_id.to_be_bytes();
+        // claimed by another run in the meantime, nothing to give up
+        let _ = self.claims.compare_and_swap(
+            tx_id.as_str(),
+            Some(&run_id[..]),
+            None as Option<&[u8]>,
+        )?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Set the latest status of `tx_id`, kept as unreported until `set_reported`
+    pub fn set_status(&self, tx_id: &TxId, status: &TxExecutionStatus) -> anyhow::Result<()> {
+        let set: TransactionResult<(), sled::Error> = (&self.statuses, &self.unreported)
+            .transaction(|(statuses, unreported)| {
+                statuses.insert(tx_id.as_str(), status.encode())?;
+                unreported.insert(tx_id.as_str(), (tx_id, status).encode())?;
+                Ok(())
+            });
+        set?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// The av-layer accepted `status`, left unreported when a later status was set meanwhile
+    pub fn set_reported(&self, tx_id: &TxId, status: &TxExecutionStatus) -> anyhow::Result<()> {
+        let _ = self.unreported.compare_and_swap(
+            tx_id.as_str(),
+            Some((tx_id, status).encode()),
+            None as Option<&[u8]>,
+        )?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Latest status of every transaction the av-layer did not accept yet
+    pub fn unreported(&self) -> anyhow::Result<Vec<(TxId, TxExecutionStatus)>> {
+        self.unreported
+            .iter()
+            .values()
+            .map(|report| Ok(Decode::decode(&mut &report?[..])?))
+            .collect()
+    }
+
+    /// Record `submission` before it is sent, replacing the previous one of `tx_id`
+    pub fn set_submission(&self, tx_id: &TxId, submission: &Submission) -> anyhow::Result<()> {
+        self.submissions
+            .insert(tx_id.as_str(), submission.encode())?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    pub fn get_submission(&self, tx_id: &TxId) -> anyhow::Result<Option<Submission>> {
+        if let Some(submission) = self.submissions.get(tx_id.as_str())? {
+            Ok(Some(Decode::decode(&mut &submission[..])?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Last `Submission` of every transaction signed so far
+    pub fn submissions(&self) -> anyhow::Result<Vec<Submission>> {
+        self.submissions
+            .iter()
+            .values()
+            .map(|submission| Ok(Decode::decode(&mut &submission?[..])?))
+            .collect()
+    }
+
+    pub fn get_status(&self, tx_id: &TxId) -> anyhow::Result<Option<TxExecutionStatus>> {
+        if let Some(status) = self.statuses.get(tx_id.as_str())? {
+            Ok(Some(Decode::decode(&mut &status[..])?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temporary() -> sled::Db {
+        sled::Config::new().temporary(true).open().unwrap()
+    }
+
+    fn tx_id() -> TxId {
+        TxId::decode(&mut &"0xabc".encode()[..]).unwrap()
+    }
+
+    #[test]
+    fn claim_is_held_until_released() {
+        let statuses = StatusDB::with_db(temporary()).unwrap();
+        let tx_id = tx_id();
+
+        assert!(statuses.claim(&tx_id).unwrap());
+        assert!(!statuses.claim(&tx_id).unwrap());
+        statuses.release(&tx_id).unwrap();
+        assert!(statuses.claim(&tx_id).unwrap());
+    }
+
+    #[test]
+    fn submission_is_kept_across_runs() {
+        let db = temporary();
+        let tx_id = tx_id();
+        let submission = Submission {
+            tx_id: tx_id.clone(),
+            network: primitives::BlockchainNetwork::Polkadot,
+            nonce: 4,
+            tx_hash: "0x01".to_string(),
+            extrinsic: vec![1, 2, 3],
+            from_block: 100,
+        };
+        let previous = StatusDB::with_db(db.clone()).unwrap();
+        assert_eq!(previous.get_submission(&tx_id).unwrap(), None);
+        previous.set_submission(&tx_id, &submission).unwrap();
+
+        let current = StatusDB::with_db(db).unwrap();
+        assert_eq!(
+            current.get_submission(&tx_id).unwrap(),
+            Some(submission.clone())
+        );
+        assert_eq!(current.submissions().unwrap(), vec![submission]);
+    }
+
+    #[test]
+    fn status_is_unreported_until_the_av_layer_accepts_it() {
+        let statuses = StatusDB::with_db(temporary()).unwrap();
+        let tx_id = tx_id();
+        let submitted = TxExecutionStatus::Submitted {
+            tx_hash: "0x01".to_string(),
+        };
+        let retracted = TxExecutionStatus::Retracted {
+            tx_hash: "0x01".to_string(),
+        };
+
+        statuses.set_status(&tx_id, &submitted).unwrap();
+        statuses.set_status(&tx_id, &retracted).unwrap();
+        // only the latest status is sent again
+        assert_eq!(
+            statuses.unreported().unwrap(),
+            vec![(tx_id.clone(), retracted.clone())]
+        );
+        // accepted after a later status was set
+        statuses.set_reported(&tx_id, &submitted).unwrap();
+        assert_eq!(statuses.unreported().unwrap().len(), 1);
+
+        statuses.set_reported(&tx_id, &retracted).unwrap();
+        assert!(statuses.unreported().unwrap().is_empty());
+        assert_eq!(statuses.get_status(&tx_id).unwrap(), Some(retracted));
+    }
+
+    #[test]
+    fn claim_of_a_previous_run_is_taken_over() {
+        let db = temporary();
+        let previous = StatusDB::with_db(db.clone()).unwrap();
+        let tx_id = tx_id();
+        assert!(previous.claim(&tx_id).unwrap());
+
+        let current = StatusDB::with_db(db).unwrap();
+
+        assert!(current.claim(&tx_id).unwrap());
+        assert!(!current.claim(&tx_id).unwrap());
+        // the previous run no longer holds the claim it could give up
+        previous.release(&tx_id).unwrap();
+        assert!(!current.claim(&tx_id).unwrap());
+    }
+}
@@ -1,9 +1,653 @@
+use crate::db::StatusDB;
+use crate::registry::RouterRegistry;
+use crate::traits::{AvLayerApi, NetworkRouter, Submission, SubmissionTracker, SubmitError};
+use futures::StreamExt;
+use network_simulation_layer::{ExecutionOutcome, SimulationBackend, Simulator};
+use primitives::{BlockchainNetwork, TxExecutionStatus, TxId, TxSimulationDelivery};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Wait before subscribing again to a lost confirmed transactions feed
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+/// Wait between two attempts to send the statuses the av-layer did not accept
+const REPORT_RETRY_INTERVAL: Duration = Duration::from_secs(10);
 
 /// Server config contains which blockchain fork simulation to spawn and connect to
+#[allow(dead_code)]
 pub struct ServerConfig {
     eth_sim: bool,
     chopsticks: bool,
     bit_sim: bool,
-    sol_sim: bool
+    sol_sim: bool,
+}
+
+/// Routes the transactions confirmed on the address verification layer to their network
+/// and reports their progress on the network back
+/// every transaction is simulated on a fork of its network before it is submitted
+pub struct RouterService<B> {
+    av_layer: Arc<dyn AvLayerApi>,
+    routers: RouterRegistry,
+    simulator: Arc<Simulator<B>>,
+    statuses: Arc<StatusDB>,
+    // Held while sending statuses so they reach the av-layer in the order they were set
+    reporting: Arc<Mutex<()>>,
+}
+
+impl<B> Clone for RouterService<B> {
+    fn clone(&self) -> Self {
+        Self {
+            av_layer: self.av_layer.clone(),
+            routers: self.routers.clone(),
+            simulator: self.simulator.clone(),
+            statuses: self.statuses.clone(),
+            reporting: self.reporting.clone(),
+        }
+    }
 }
 
+impl<B: SimulationBackend + 'static> RouterService<B> {
+    pub fn new(
+        av_layer: impl AvLayerApi + 'static,
+        routers: RouterRegistry,
+        simulator: Simulator<B>,
+        statuses: StatusDB,
+    ) -> Self {
+        Self {
+            av_layer: Arc::new(av_layer),
+            routers,
+            simulator: Arc::new(simulator),
+            statuses: Arc::new(statuses),
+            reporting: Arc::default(),
+        }
+    }
+
+    /// Consume the confirmed transactions feed, subscribing again whenever it is lost
+    /// the transactions a previous run left on their way to the network are tracked again first
+    pub async fn run(self) -> anyhow::Result<()> {
+        self.resume_submissions()?;
+        tokio::spawn(self.clone().resend_reports());
+        loop {
+            match self.av_layer.confirmed_txs().await {
+                Ok(mut deliveries) => {
+                    tracing::info!("listening to confirmed transactions");
+                    while let Some(delivery) = deliveries.next().await {
+                        // left unacknowledged, the av-layer delivers it again
+                        let delivery = match delivery {
+                            Ok(delivery) => delivery,
+                            Err(err) => {
+                                tracing::error!("failed to decode a delivery: {err}");
+                                continue;
+                            }
+                        };
+                        // a slow network does not hold back the transactions of the others
+                        let service = self.clone();
+                        tokio::spawn(async move {
+                            let delivery_id = delivery.delivery_id;
+                            if let Err(err) = service.route(delivery).await {
+                                tracing::error!("failed to route delivery {delivery_id}: {err}");
+                            }
+                        });
+                    }
+                    tracing::warn!("confirmed transactions feed closed");
+                }
+                Err(err) => tracing::error!("failed to subscribe to confirmed transactions: {err}"),
+            }
+            tokio::time::sleep(RECONNECT_INTERVAL).await;
+        }
+    }
+
+    /// Simulate the delivered transaction then submit it through the router of its network
+    /// and report every status of the transaction until its final one
+    /// the delivery is acknowledged once submitted so the transaction is never submitted twice
+    /// and left unacknowledged when the network is unavailable so it is delivered again
+    /// every signed transaction is recorded before it is submitted so a later run resumes it
+    pub async fn route(&self, delivery: TxSimulationDelivery) -> anyhow::Result<()> {
+        let tx = delivery.tx;
+        let tx_id = tx.get_tx_id();
+        let network = tx.get_network();
+        // delivered again after a restart, the transaction already reached the network
+        if let Some(status) = self.statuses.get_status(&tx_id)? {
+            tracing::warn!("transaction {tx_id} already routed: {status:?}");
+            self.av_layer.ack_confirmed_tx(delivery.delivery_id).await?;
+            return Ok(());
+        }
+
+        let Some(router) = self.routers.get(&network) else {
+            anyhow::bail!("No router for {network:?}, transaction {tx_id} left for redelivery");
+        };
+        // delivered again while a submission is in flight, the first delivery acknowledges it
+        if !self.statuses.claim(&tx_id)? {
+            tracing::warn!("transaction {tx_id} already being submitted");
+            return Ok(());
+        }
+        // a previous run stopped while submitting, the network tells whether the transaction reached it
+        let resumed = match self.statuses.get_submission(&tx_id)? {
+            Some(submission) => {
+                tracing::warn!("resuming the submission of transaction {tx_id}");
+                router.resume(&submission).await.transpose()
+            }
+            None => None,
+        };
+        let submitted = if let Some(resumed) = resumed {
+            resumed
+        } else {
+            // a transfer failing on the fork would fail the same way on the network
+            let report = match self.simulator.simulate(&tx.get_sender_address(), &tx).await {
+                Ok(report) => report,
+                Err(err) => {
+                    self.statuses.release(&tx_id)?;
+                    anyhow::bail!(
+                        "transaction {tx_id} left for redelivery, simulation failed: {err}"
+                    );
+                }
+            };
+            tracing::info!("transaction {tx_id} simulated: {report:?}");
+            if let ExecutionOutcome::Failed(reason) = report.outcome {
+                let status = TxExecutionStatus::Failed {
+                    reason: format!("Simulation failed: {reason}"),
+                };
+                self.report(&tx_id, status).await?;
+                self.av_layer.ack_confirmed_tx(delivery.delivery_id).await?;
+                return Ok(());
+            }
+            let record = |submission: &Submission| self.statuses.set_submission(&tx_id, submission);
+            router.submit(&tx, &record).await
+        };
+        let tracker = match submitted {
+            Ok(tracker) => tracker,
+            Err(SubmitError::Rejected(err)) => {
+                let status = TxExecutionStatus::Failed {
+                    reason: err.to_string(),
+                };
+                self.report(&tx_id, status).await?;
+                self.av_layer.ack_confirmed_tx(delivery.delivery_id).await?;
+                return Ok(());
+            }
+            Err(err @ SubmitError::Unavailable(_)) => {
+                self.statuses.release(&tx_id)?;
+                anyhow::bail!("transaction {tx_id} left for redelivery: {err}");
+            }
+        };
+        let tx_hash = tracker.tx_hash();
+        self.report(&tx_id, TxExecutionStatus::Submitted { tx_hash })
+            .await?;
+        self.av_layer.ack_confirmed_tx(delivery.delivery_id).await?;
+        self.track(&tx_id, &network, tracker).await
+    }
+
+    /// Report every status of a submitted transaction until its final one
+    async fn track(
+        &self,
+        tx_id: &TxId,
+        network: &BlockchainNetwork,
+        mut tracker: Box<dyn SubmissionTracker>,
+    ) -> anyhow::Result<()> {
+        while let Some(status) = tracker.next_status().await {
+            let status = status.unwrap_or_else(|err| TxExecutionStatus::Failed {
+                reason: err.to_string(),
+            });
+            let is_final = status.is_final();
+            self.report(tx_id, status).await?;
+            if is_final {
+                return Ok(());
+            }
+        }
+        tracing::warn!("{network:?} stopped reporting on transaction {tx_id}");
+        Ok(())
+    }
+
+    /// Track again the transactions a previous run reported as submitted but not as final
+    /// the ones it never reported are left unacknowledged, their redelivery resumes them
+    fn resume_submissions(&self) -> anyhow::Result<()> {
+        for submission in self.statuses.submissions()? {
+            let tx_id = submission.tx_id.clone();
+            let Some(status) = self.statuses.get_status(&tx_id)? else {
+                continue;
+            };
+            if status.is_final() {
+                continue;
+            }
+            let Some(router) = self.routers.get(&submission.network) else {
+                tracing::warn!(
+                    "No router for {:?}, transaction {tx_id} not resumed",
+                    submission.network
+                );
+                continue;
+            };
+            if !self.statuses.claim(&tx_id)? {
+                continue;
+            }
+            tracing::info!("resuming transaction {tx_id}: {status:?}");
+            let service = self.clone();
+            tokio::spawn(async move {
+                if let Err(err) = service.resume_submission(router, submission).await {
+                    tracing::error!("failed to resume transaction {tx_id}: {err}");
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Follow a transaction submitted by a previous run until its final status
+    async fn resume_submission(
+        &self,
+        router: Arc<dyn NetworkRouter>,
+        submission: Submission,
+    ) -> anyhow::Result<()> {
+        let tx_id = &submission.tx_id;
+        loop {
+            match router.resume(&submission).await {
+                Ok(Some(tracker)) => return self.track(tx_id, &submission.network, tracker).await,
+                // accepted by the network then replaced by another transaction of the relayer
+                Ok(None) => {
+                    let tx_hash = submission.tx_hash.clone();
+                    return self
+                        .report(tx_id, TxExecutionStatus::Usurped { tx_hash })
+                        .await;
+                }
+                Err(SubmitError::Rejected(err)) => {
+                    let status = TxExecutionStatus::Failed {
+                        reason: err.to_string(),
+                    };
+                    return self.report(tx_id, status).await;
+                }
+                Err(err @ SubmitError::Unavailable(_)) => {
+                    tracing::warn!("failed to resume transaction {tx_id}, retrying: {err}");
+                    tokio::time::sleep(RECONNECT_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    /// Persist the transaction status and push it to the av-layer
+    /// a status the av-layer did not accept is sent again by `resend_reports`
+    async fn report(&self, tx_id: &TxId, status: TxExecutionStatus) -> anyhow::Result<()> {
+        tracing::info!("transaction {tx_id}: {status:?}");
+        let _reporting = self.reporting.lock().await;
+        self.statuses.set_status(tx_id, &status)?;
+        match self
+            .av_layer
+            .report_tx_status(tx_id.clone(), status.clone())
+            .await
+        {
+            Ok(()) => self.statuses.set_reported(tx_id, &status)?,
+            Err(err) => tracing::error!("failed to report transaction {tx_id} status: {err}"),
+        }
+        Ok(())
+    }
+
+    /// Send the statuses the av-layer did not accept again, until it does
+    async fn resend_reports(self) {
+        loop {
+            tokio::time::sleep(REPORT_RETRY_INTERVAL).await;
+            if let Err(err) = self.send_unreported().await {
+                tracing::warn!("failed to send unreported statuses: {err}");
+            }
+        }
+    }
+
+    async fn send_unreported(&self) -> anyhow::Result<()> {
+        let _reporting = self.reporting.lock().await;
+        for (tx_id, status) in self.statuses.unreported()? {
+            self.av_layer
+                .report_tx_status(tx_id.clone(), status.clone())
+                .await?;
+            self.statuses.set_reported(&tx_id, &status)?;
+            tracing::info!("reported transaction {tx_id} status again: {status:?}");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::RecordSubmission;
+    use futures::stream::BoxStream;
+    use network_simulation_layer::mock::MockBackend;
+    use primitives::{
+        BlockchainNetwork, TxConfirmationObject, TxObject, TxSimulationObject, VaneCallData,
+        VaneMultiAddress,
+    };
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use subxt::utils::{AccountId32, MultiAddress};
+
+    const SENDER: [u8; 32] = [1; 32];
+    const DELIVERY_ID: u64 = 7;
+
+    /// Records the acknowledgements and reports of the router
+    #[derive(Default)]
+    struct MockAvLayer {
+        acks: Mutex<Vec<u64>>,
+        reports: Mutex<Vec<TxExecutionStatus>>,
+        // statuses are refused as by an unreachable av-layer
+        refuse_reports: AtomicBool,
+    }
+
+    #[async_trait::async_trait]
+    impl AvLayerApi for Arc<MockAvLayer> {
+        async fn confirmed_txs(
+            &self,
+        ) -> anyhow::Result<BoxStream<'static, anyhow::Result<TxSimulationDelivery>>> {
+            Ok(futures::stream::empty().boxed())
+        }
+
+        async fn ack_confirmed_tx(&self, delivery_id: u64) -> anyhow::Result<()> {
+            self.acks.lock().unwrap().push(delivery_id);
+            Ok(())
+        }
+
+        async fn report_tx_status(
+            &self,
+            _tx_id: TxId,
+            status: TxExecutionStatus,
+        ) -> anyhow::Result<()> {
+            if self.refuse_reports.load(Ordering::SeqCst) {
+                anyhow::bail!("connection lost");
+            }
+            self.reports.lock().unwrap().push(status);
+            Ok(())
+        }
+    }
+
+    /// Router whose network is either unavailable or finalizes every transaction
+    #[derive(Clone, Default)]
+    struct MockRouter {
+        unavailable: bool,
+        // another transaction used the nonce of the resumed submissions
+        usurped: bool,
+        submissions: Arc<AtomicUsize>,
+        resumed: Arc<Mutex<Vec<Submission>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl NetworkRouter for MockRouter {
+        async fn submit(
+            &self,
+            tx: &TxSimulationObject,
+            record: &RecordSubmission<'_>,
+        ) -> Result<Box<dyn SubmissionTracker>, SubmitError> {
+            self.submissions.fetch_add(1, Ordering::SeqCst);
+            if self.unavailable {
+                return Err(SubmitError::Unavailable(anyhow::anyhow!("connection lost")));
+            }
+            record(&submission(&tx.get_tx_id()))?;
+            Ok(Box::new(MockTracker { finalized: false }))
+        }
+
+        async fn resume(
+            &self,
+            submission: &Submission,
+        ) -> Result<Option<Box<dyn SubmissionTracker>>, SubmitError> {
+            self.resumed.lock().unwrap().push(submission.clone());
+            if self.usurped {
+                return Ok(None);
+            }
+            Ok(Some(Box::new(MockTracker { finalized: false })))
+        }
+    }
+
+    struct MockTracker {
+        finalized: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl SubmissionTracker for MockTracker {
+        fn tx_hash(&self) -> String {
+            "0x01".to_string()
+        }
+
+        async fn next_status(&mut self) -> Option<anyhow::Result<TxExecutionStatus>> {
+            if self.finalized {
+                return None;
+            }
+            self.finalized = true;
+            Some(Ok(finalized()))
+        }
+    }
+
+    fn finalized() -> TxExecutionStatus {
+        TxExecutionStatus::Finalized {
+            tx_hash: "0x01".to_string(),
+            block_hash: "0x02".to_string(),
+            extrinsic_index: 1,
+        }
+    }
+
+    fn submission(tx_id: &TxId) -> Submission {
+        Submission {
+            tx_id: tx_id.clone(),
+            network: BlockchainNetwork::Polkadot,
+            nonce: 3,
+            tx_hash: "0x01".to_string(),
+            extrinsic: vec![1, 2, 3],
+            from_block: 100,
+        }
+    }
+
+    fn delivery() -> TxSimulationDelivery {
+        let network = BlockchainNetwork::Polkadot;
+        let tx = TxObject::new(
+            VaneCallData::new(network.clone(), 100),
+            MultiAddress::Id(AccountId32(SENDER)),
+            MultiAddress::Id(AccountId32([2; 32])),
+            network,
+        );
+        TxSimulationDelivery {
+            delivery_id: DELIVERY_ID,
+            tx: TxConfirmationObject::from(tx).into(),
+        }
+    }
+
+    fn service(av_layer: &Arc<MockAvLayer>, router: &MockRouter) -> RouterService<MockBackend> {
+        funded_service(av_layer, router, 1_000)
+    }
+
+    /// Service simulating on a fork where the sender holds `balance`
+    fn funded_service(
+        av_layer: &Arc<MockAvLayer>,
+        router: &MockRouter,
+        balance: u128,
+    ) -> RouterService<MockBackend> {
+        let mut backend = MockBackend::new(1);
+        backend.set_balance(
+            BlockchainNetwork::Polkadot,
+            VaneMultiAddress::Id(AccountId32(SENDER)),
+            balance,
+        );
+        let mut routers = RouterRegistry::default();
+        routers.register(BlockchainNetwork::Polkadot, router.clone());
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        RouterService::new(
+            av_layer.clone(),
+            routers,
+            Simulator::new(backend),
+            StatusDB::with_db(db).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn delivery_is_acknowledged_once_submitted() {
+        let (av_layer, router) = (Arc::new(MockAvLayer::default()), MockRouter::default());
+        let service = service(&av_layer, &router);
+        let delivery = delivery();
+        let tx_id = delivery.tx.get_tx_id();
+
+        service.route(delivery).await.unwrap();
+
+        assert_eq!(*av_layer.acks.lock().unwrap(), vec![DELIVERY_ID]);
+        let submitted = TxExecutionStatus::Submitted {
+            tx_hash: "0x01".to_string(),
+        };
+        assert_eq!(
+            *av_layer.reports.lock().unwrap(),
+            vec![submitted, finalized()]
+        );
+        assert_eq!(
+            service.statuses.get_status(&tx_id).unwrap(),
+            Some(finalized())
+        );
+        assert_eq!(
+            service.statuses.get_submission(&tx_id).unwrap(),
+            Some(submission(&tx_id))
+        );
+    }
+
+    #[tokio::test]
+    async fn transfer_the_sender_cannot_afford_is_failed_without_submitting() {
+        let (av_layer, router) = (Arc::new(MockAvLayer::default()), MockRouter::default());
+        // the transfer moves the sender's funds, whatever the relayer holds
+        let service = funded_service(&av_layer, &router, 10);
+        let delivery = delivery();
+        let tx_id = delivery.tx.get_tx_id();
+
+        service.route(delivery).await.unwrap();
+
+        assert_eq!(router.submissions.load(Ordering::SeqCst), 0);
+        assert_eq!(*av_layer.acks.lock().unwrap(), vec![DELIVERY_ID]);
+        assert!(matches!(
+            service.statuses.get_status(&tx_id).unwrap(),
+            Some(TxExecutionStatus::Failed { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn recorded_submission_is_resumed_instead_of_signed_again() {
+        let (av_layer, router) = (Arc::new(MockAvLayer::default()), MockRouter::default());
+        let service = service(&av_layer, &router);
+        let delivery = delivery();
+        let tx_id = delivery.tx.get_tx_id();
+        // a previous run signed the transaction and stopped before reporting it
+        service
+            .statuses
+            .set_submission(&tx_id, &submission(&tx_id))
+            .unwrap();
+
+        service.route(delivery).await.unwrap();
+
+        assert_eq!(router.submissions.load(Ordering::SeqCst), 0);
+        assert_eq!(*router.resumed.lock().unwrap(), vec![submission(&tx_id)]);
+        assert_eq!(*av_layer.acks.lock().unwrap(), vec![DELIVERY_ID]);
+        assert_eq!(
+            service.statuses.get_status(&tx_id).unwrap(),
+            Some(finalized())
+        );
+    }
+
+    #[tokio::test]
+    async fn delivery_is_left_unacknowledged_when_the_network_is_unavailable() {
+        let av_layer = Arc::new(MockAvLayer::default());
+        let router = MockRouter {
+            unavailable: true,
+            ..Default::default()
+        };
+        let service = service(&av_layer, &router);
+        let delivery = delivery();
+        let tx_id = delivery.tx.get_tx_id();
+
+        assert!(service.route(delivery).await.is_err());
+
+        assert!(av_layer.acks.lock().unwrap().is_empty());
+        assert!(av_layer.reports.lock().unwrap().is_empty());
+        // the redelivery submits it again
+        assert!(service.statuses.claim(&tx_id).unwrap());
+    }
+
+    #[tokio::test]
+    async fn delivery_of_a_routed_transaction_is_acknowledged_without_submitting() {
+        let (av_layer, router) = (Arc::new(MockAvLayer::default()), MockRouter::default());
+        let service = service(&av_layer, &router);
+        let delivery = delivery();
+        service
+            .statuses
+            .set_status(&delivery.tx.get_tx_id(), &finalized())
+            .unwrap();
+
+        service.route(delivery).await.unwrap();
+
+        assert_eq!(*av_layer.acks.lock().unwrap(), vec![DELIVERY_ID]);
+        assert_eq!(router.submissions.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn recorded_submission_whose_nonce_was_used_by_another_is_signed_again() {
+        let av_layer = Arc::new(MockAvLayer::default());
+        let router = MockRouter {
+            usurped: true,
+            ..Default::default()
+        };
+        let service = service(&av_layer, &router);
+        let delivery = delivery();
+        let tx_id = delivery.tx.get_tx_id();
+        service
+            .statuses
+            .set_submission(&tx_id, &submission(&tx_id))
+            .unwrap();
+
+        service.route(delivery).await.unwrap();
+
+        assert_eq!(router.resumed.lock().unwrap().len(), 1);
+        assert_eq!(router.submissions.load(Ordering::SeqCst), 1);
+        assert_eq!(*av_layer.acks.lock().unwrap(), vec![DELIVERY_ID]);
+    }
+
+    #[tokio::test]
+    async fn refused_status_is_sent_again_until_accepted() {
+        let (av_layer, router) = (Arc::new(MockAvLayer::default()), MockRouter::default());
+        av_layer.refuse_reports.store(true, Ordering::SeqCst);
+        let service = service(&av_layer, &router);
+        let delivery = delivery();
+        let tx_id = delivery.tx.get_tx_id();
+
+        service.route(delivery).await.unwrap();
+        assert!(av_layer.reports.lock().unwrap().is_empty());
+        assert_eq!(
+            service.statuses.unreported().unwrap(),
+            vec![(tx_id.clone(), finalized())]
+        );
+        assert!(service.send_unreported().await.is_err());
+
+        av_layer.refuse_reports.store(false, Ordering::SeqCst);
+        service.send_unreported().await.unwrap();
+
+        assert_eq!(*av_layer.reports.lock().unwrap(), vec![finalized()]);
+        assert!(service.statuses.unreported().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn submitted_transactions_of_a_previous_run_are_tracked_again() {
+        let (av_layer, router) = (Arc::new(MockAvLayer::default()), MockRouter::default());
+        let service = service(&av_layer, &router);
+        let submitted = TxExecutionStatus::Submitted {
+            tx_hash: "0x01".to_string(),
+        };
+        let pending = delivery().tx.get_tx_id();
+        service
+            .statuses
+            .set_submission(&pending, &submission(&pending))
+            .unwrap();
+        service.statuses.set_status(&pending, &submitted).unwrap();
+        let done = delivery().tx.get_tx_id();
+        service
+            .statuses
+            .set_submission(&done, &submission(&done))
+            .unwrap();
+        service.statuses.set_status(&done, &finalized()).unwrap();
+
+        service.resume_submissions().unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while service.statuses.get_status(&pending).unwrap() != Some(finalized()) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // the final transaction is left alone
+        assert_eq!(*router.resumed.lock().unwrap(), vec![submission(&pending)]);
+        assert_eq!(*av_layer.reports.lock().unwrap(), vec![finalized()]);
+        assert!(av_layer.acks.lock().unwrap().is_empty());
+    }
+}
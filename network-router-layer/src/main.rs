@@ -1,3 +1,104 @@
-fn main() {
-    println!("Hello, world!");
+use clap::Parser;
+use network_simulation_layer::{substrate::SubstrateForkBackend, Simulator};
+use primitives::BlockchainNetwork;
+use std::{path::PathBuf, str::FromStr, sync::Arc};
+use subxt_signer::{sr25519::Keypair, SecretUri};
+
+mod av_layer;
+mod db;
+mod handler;
+mod nonce;
+mod registry;
+mod substrate;
+mod traits;
+
+use av_layer::AvLayerConnection;
+use db::StatusDB;
+use handler::RouterService;
+use nonce::NonceManager;
+use registry::RouterRegistry;
+use substrate::SubstrateRouter;
+
+/// Network router layer cli arguments
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct RouterCli {
+    /// Websocket url of the address verification layer router endpoint
+    #[arg(long, default_value = "ws://127.0.0.1:8001")]
+    av_layer_url: String,
+    /// File holding the secret the av-layer router endpoint was started with
+    #[arg(long)]
+    router_secret_file: PathBuf,
+    /// Substrate node to submit to as `network=url`, e.g. `polkadot=wss://rpc.polkadot.io`
+    #[arg(long = "substrate-node", value_parser = parse_substrate_node)]
+    substrate_nodes: Vec<(BlockchainNetwork, String)>,
+    /// Chopsticks fork simulating the transactions of a Substrate network as `network=url`
+    /// required for every `--substrate-node`, e.g. `polkadot=ws://127.0.0.1:8100`
+    #[arg(long = "fork-node", value_parser = parse_substrate_node)]
+    fork_nodes: Vec<(BlockchainNetwork, String)>,
+    /// Secret uri of the sr25519 relayer account signing Substrate transactions
+    /// as the proxy of their senders, who must have added it with `Proxy::add_proxy`
+    #[arg(long)]
+    relayer_seed: Option<String>,
+    /// Directory of the on-disk database keeping the status of the routed transactions
+    #[arg(long, default_value = "vane-router-db")]
+    db_path: PathBuf,
+}
+
+fn parse_substrate_node(node: &str) -> Result<(BlockchainNetwork, String), String> {
+    let (name, url) = node
+        .split_once('=')
+        .ok_or("expected `network=url`".to_string())?;
+    [
+        BlockchainNetwork::Polkadot,
+        BlockchainNetwork::Kusama,
+        BlockchainNetwork::Astar,
+    ]
+    .into_iter()
+    .find(|network| format!("{network:?}").eq_ignore_ascii_case(name))
+    .map(|network| (network, url.to_string()))
+    .ok_or(format!("{name} is not a supported Substrate network"))
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt().compact().init();
+    let args = RouterCli::parse();
+
+    let mut routers = RouterRegistry::default();
+    let nonces = Arc::new(NonceManager::default());
+    let mut forks = SubstrateForkBackend::default();
+    if !args.substrate_nodes.is_empty() {
+        let seed = args.relayer_seed.ok_or(anyhow::anyhow!(
+            "--relayer-seed is required to submit to Substrate networks"
+        ))?;
+        let relayer = Keypair::from_uri(&SecretUri::from_str(&seed)?)?;
+        for (network, url) in args.substrate_nodes {
+            // transactions are only submitted once they succeeded on a fork of their network
+            let (_, fork_url) = args
+                .fork_nodes
+                .iter()
+                .find(|(forked, _)| forked == &network)
+                .ok_or(anyhow::anyhow!("--fork-node is required for {network:?}"))?;
+            forks = forks.with_node(network.clone(), fork_url.clone(), relayer.clone())?;
+            tracing::info!("simulating {network:?} transactions on {fork_url}");
+
+            let router =
+                SubstrateRouter::connect(network.clone(), &url, relayer.clone(), nonces.clone())
+                    .await?;
+            tracing::info!("routing {network:?} transactions to {url}");
+            routers.register(network, router);
+        }
+    }
+
+    // the router endpoint refuses connections without the shared secret
+    let secret = std::fs::read_to_string(&args.router_secret_file)?;
+    let av_layer = AvLayerConnection::connect(&args.av_layer_url, secret.trim()).await?;
+    tracing::info!("connected to av-layer at {}", args.av_layer_url);
+
+    let statuses = StatusDB::open(&args.db_path)?;
+
+    RouterService::new(av_layer, routers, Simulator::new(forks), statuses)
+        .run()
+        .await
 }
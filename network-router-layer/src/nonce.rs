@@ -0,0 +1,361 @@
+use primitives::BlockchainNetwork;
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use tokio::sync::Mutex;
+
+/// Submissions of a transaction retried with a fresh nonce before giving up
+pub const MAX_NONCE_ATTEMPTS: usize = 3;
+
+/// Chain access needed to keep the relayer nonces in sync
+#[async_trait::async_trait]
+pub trait NonceSource: Send + Sync {
+    /// Next nonce of `account` known to the chain
+    async fn account_nonce(&self, account: &[u8]) -> anyhow::Result<u64>;
+}
+
+/// Submission failure caused by the nonce the transaction was signed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonceFault {
+    /// The nonce was already used on chain or by a transaction in the pool
+    Stale,
+    /// The nonce is ahead of the chain, a lower nonce is missing
+    Future,
+}
+
+impl NonceFault {
+    /// Nonce fault reported by the node in the submission error, if any
+    pub fn classify(err: &anyhow::Error) -> Option<Self> {
+        let reason = err.to_string().to_lowercase();
+        if reason.contains("outdated")
+            || reason.contains("stale")
+            || reason.contains("priority is too low")
+            || reason.contains("nonce too low")
+        {
+            Some(NonceFault::Stale)
+        } else if reason.contains("future") || reason.contains("nonce too high") {
+            Some(NonceFault::Future)
+        } else {
+            None
+        }
+    }
+}
+
+/// Nonces of one relayer account on one network
+#[derive(Debug, Default)]
+struct AccountNonces {
+    // Next nonce never handed out
+    next: u64,
+    // Nonces handed out whose submission is not settled yet
+    in_flight: BTreeSet<u64>,
+    // Nonces handed out but never used, handed out again first to fill the gap
+    released: BTreeSet<u64>,
+}
+
+impl AccountNonces {
+    fn reserve(&mut self, chain_nonce: u64) -> u64 {
+        // anything below the chain nonce got used meanwhile
+        self.released.retain(|nonce| *nonce >= chain_nonce);
+        let nonce = match self.released.pop_first() {
+            Some(nonce) => nonce,
+            None => {
+                let mut nonce = self.next.max(chain_nonce);
+                // a resync may have moved back below nonces still in flight
+                while self.in_flight.contains(&nonce) {
+                    nonce += 1;
+                }
+                self.next = nonce + 1;
+                nonce
+            }
+        };
+        self.in_flight.insert(nonce);
+        nonce
+    }
+
+    fn resync(&mut self, fault: NonceFault, chain_nonce: u64) {
+        self.released.clear();
+        self.next = match fault {
+            // never hand out again what the pool may still hold
+            NonceFault::Stale => self.next.max(chain_nonce),
+            // the nonces between the chain and the rejected one got lost, fill the gap
+            NonceFault::Future => chain_nonce,
+        };
+    }
+}
+
+/// Hands out the nonces of the relayer accounts per network
+/// every nonce is fresh from the chain and reserved while its submission is in flight
+/// so concurrent submissions of the same relayer never share a nonce
+#[derive(Debug, Default)]
+pub struct NonceManager {
+    accounts: Mutex<HashMap<(BlockchainNetwork, Vec<u8>), AccountNonces>>,
+}
+
+impl NonceManager {
+    /// Fresh nonce of `account` on `network` reserved until settled with `complete` or `release`
+    pub async fn reserve(
+        &self,
+        network: &BlockchainNetwork,
+        account: &[u8],
+        chain: &dyn NonceSource,
+    ) -> anyhow::Result<u64> {
+        let chain_nonce = chain.account_nonce(account).await?;
+        let mut accounts = self.accounts.lock().await;
+        Ok(accounts
+            .entry((network.clone(), account.to_vec()))
+            .or_default()
+            .reserve(chain_nonce))
+    }
+
+    /// The transaction signed with `nonce` was accepted by the network
+    pub async fn complete(&self, network: &BlockchainNetwork, account: &[u8], nonce: u64) {
+        let mut accounts = self.accounts.lock().await;
+        if let Some(nonces) = accounts.get_mut(&(network.clone(), account.to_vec())) {
+            nonces.in_flight.remove(&nonce);
+        }
+    }
+
+    /// The transaction signed with `nonce` never reached the network, the nonce is free again
+    pub async fn release(&self, network: &BlockchainNetwork, account: &[u8], nonce: u64) {
+        let mut accounts = self.accounts.lock().await;
+        if let Some(nonces) = accounts.get_mut(&(network.clone(), account.to_vec())) {
+            nonces.in_flight.remove(&nonce);
+            nonces.released.insert(nonce);
+        }
+    }
+
+    /// Drop `nonce` and resync the account from the chain after the network rejected it
+    pub async fn resync(
+        &self,
+        network: &BlockchainNetwork,
+        account: &[u8],
+        nonce: u64,
+        fault: NonceFault,
+        chain: &dyn NonceSource,
+    ) -> anyhow::Result<()> {
+        let chain_nonce = chain.account_nonce(account).await?;
+        let mut accounts = self.accounts.lock().await;
+        let nonces = accounts
+            .entry((network.clone(), account.to_vec()))
+            .or_default();
+        nonces.in_flight.remove(&nonce);
+        nonces.resync(fault, chain_nonce);
+        Ok(())
+    }
+
+    /// Run `submit` with a fresh nonce of `account`, retrying with a new nonce
+    /// up to `MAX_NONCE_ATTEMPTS` times while the network rejects the nonce
+    pub async fn submit_with_nonce<T, F, Fut>(
+        &self,
+        network: &BlockchainNetwork,
+        account: &[u8],
+        chain: &dyn NonceSource,
+        submit: F,
+    ) -> anyhow::Result<T>
+    where
+        F: Fn(u64) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let nonce = self.reserve(network, account, chain).await?;
+            let err = match submit(nonce).await {
+                Ok(submitted) => {
+                    self.complete(network, account, nonce).await;
+                    return Ok(submitted);
+                }
+                Err(err) => err,
+            };
+
+            match NonceFault::classify(&err) {
+                Some(fault) if attempt < MAX_NONCE_ATTEMPTS => {
+                    tracing::warn!("{network:?} rejected nonce {nonce} as {fault:?}, resyncing");
+                    self.resync(network, account, nonce, fault, chain).await?;
+                    attempt += 1;
+                }
+                Some(fault) => {
+                    self.resync(network, account, nonce, fault, chain).await?;
+                    return Err(err);
+                }
+                None => {
+                    self.release(network, account, nonce).await;
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    const RELAYER: &[u8] = &[1; 32];
+
+    /// Chain whose relayer nonce is set by the test
+    #[derive(Default)]
+    struct MockChain {
+        nonce: AtomicU64,
+    }
+
+    impl MockChain {
+        fn at(nonce: u64) -> Self {
+            Self {
+                nonce: AtomicU64::new(nonce),
+            }
+        }
+
+        fn set(&self, nonce: u64) {
+            self.nonce.store(nonce, Ordering::SeqCst);
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl NonceSource for MockChain {
+        async fn account_nonce(&self, _account: &[u8]) -> anyhow::Result<u64> {
+            Ok(self.nonce.load(Ordering::SeqCst))
+        }
+    }
+
+    fn polkadot() -> BlockchainNetwork {
+        BlockchainNetwork::Polkadot
+    }
+
+    #[tokio::test]
+    async fn in_flight_submissions_get_distinct_nonces() {
+        let chain = MockChain::at(7);
+        let nonces = NonceManager::default();
+
+        let first = nonces.reserve(&polkadot(), RELAYER, &chain).await.unwrap();
+        let second = nonces.reserve(&polkadot(), RELAYER, &chain).await.unwrap();
+        // the other network keeps its own nonces
+        let kusama = nonces
+            .reserve(&BlockchainNetwork::Kusama, RELAYER, &chain)
+            .await
+            .unwrap();
+
+        assert_eq!((first, second, kusama), (7, 8, 7));
+    }
+
+    #[tokio::test]
+    async fn chain_ahead_of_local_nonce_wins() {
+        let chain = MockChain::at(3);
+        let nonces = NonceManager::default();
+        let nonce = nonces.reserve(&polkadot(), RELAYER, &chain).await.unwrap();
+        nonces.complete(&polkadot(), RELAYER, nonce).await;
+
+        // the relayer account was used outside of the router
+        chain.set(10);
+        assert_eq!(
+            nonces.reserve(&polkadot(), RELAYER, &chain).await.unwrap(),
+            10
+        );
+    }
+
+    #[tokio::test]
+    async fn released_nonce_fills_the_gap() {
+        let chain = MockChain::at(0);
+        let nonces = NonceManager::default();
+        let first = nonces.reserve(&polkadot(), RELAYER, &chain).await.unwrap();
+        let second = nonces.reserve(&polkadot(), RELAYER, &chain).await.unwrap();
+
+        nonces.release(&polkadot(), RELAYER, first).await;
+        assert_eq!(
+            nonces.reserve(&polkadot(), RELAYER, &chain).await.unwrap(),
+            first
+        );
+        assert_eq!(
+            nonces.reserve(&polkadot(), RELAYER, &chain).await.unwrap(),
+            second + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn stale_nonce_is_retried_with_a_fresh_one() {
+        let chain = MockChain::at(5);
+        let nonces = NonceManager::default();
+        let attempts = Mutex::new(Vec::new());
+
+        let submitted = nonces
+            .submit_with_nonce(&polkadot(), RELAYER, &chain, |nonce| {
+                let attempts = &attempts;
+                let chain = &chain;
+                async move {
+                    attempts.lock().await.push(nonce);
+                    if nonce < 6 {
+                        // another submitter used the nonce meanwhile
+                        chain.set(6);
+                        anyhow::bail!("Invalid Transaction (1010): Transaction is outdated")
+                    }
+                    Ok(nonce)
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(submitted, 6);
+        assert_eq!(*attempts.lock().await, vec![5, 6]);
+    }
+
+    #[tokio::test]
+    async fn future_nonce_resyncs_from_chain() {
+        let chain = MockChain::at(0);
+        let nonces = NonceManager::default();
+        // nonces handed out for submissions which got dropped by the network
+        for _ in 0..3 {
+            let nonce = nonces.reserve(&polkadot(), RELAYER, &chain).await.unwrap();
+            nonces.complete(&polkadot(), RELAYER, nonce).await;
+        }
+
+        let submitted = nonces
+            .submit_with_nonce(&polkadot(), RELAYER, &chain, |nonce| async move {
+                if nonce > 0 {
+                    anyhow::bail!("Transaction will be valid in the future")
+                }
+                Ok(nonce)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(submitted, 0);
+    }
+
+    #[tokio::test]
+    async fn other_failures_are_not_retried() {
+        let chain = MockChain::at(2);
+        let nonces = NonceManager::default();
+        let attempts = AtomicU64::new(0);
+
+        let result = nonces
+            .submit_with_nonce(&polkadot(), RELAYER, &chain, |_| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                async { anyhow::bail!("Inability to pay some fees") as anyhow::Result<()> }
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        // the unused nonce is handed out again
+        assert_eq!(
+            nonces.reserve(&polkadot(), RELAYER, &chain).await.unwrap(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let chain = MockChain::at(0);
+        let nonces = NonceManager::default();
+        let attempts = AtomicU64::new(0);
+
+        let result = nonces
+            .submit_with_nonce(&polkadot(), RELAYER, &chain, |_| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                async { anyhow::bail!("Priority is too low") as anyhow::Result<()> }
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), MAX_NONCE_ATTEMPTS as u64);
+    }
+}
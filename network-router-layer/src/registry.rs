@@ -0,0 +1,20 @@
+use crate::traits::NetworkRouter;
+use primitives::BlockchainNetwork;
+use std::{collections::HashMap, sync::Arc};
+
+/// Network router of every network the relayer submits to
+#[derive(Clone, Default)]
+pub struct RouterRegistry {
+    routers: HashMap<BlockchainNetwork, Arc<dyn NetworkRouter>>,
+}
+
+impl RouterRegistry {
+    /// Set the router of `network`, replacing the previous one
+    pub fn register(&mut self, network: BlockchainNetwork, router: impl NetworkRouter + 'static) {
+        self.routers.insert(network, Arc::new(router));
+    }
+
+    pub fn get(&self, network: &BlockchainNetwork) -> Option<Arc<dyn NetworkRouter>> {
+        self.routers.get(network).cloned()
+    }
+}
@@ -0,0 +1,424 @@
+use crate::nonce::{NonceFault, NonceManager, NonceSource};
+use crate::traits::{NetworkRouter, RecordSubmission, Submission, SubmissionTracker, SubmitError};
+use network_simulation_layer::substrate::{proxied_call_error, proxied_transfer};
+use parity_scale_codec::Decode;
+use primitives::{BlockchainNetwork, NetworkFamily, TxExecutionStatus, TxSimulationObject};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use subxt::backend::rpc::{rpc_params, RpcClient};
+use subxt::config::polkadot::PolkadotExtrinsicParamsBuilder;
+use subxt::dynamic::Value;
+use subxt::error::DispatchError;
+use subxt::ext::scale_value::At;
+use subxt::tx::{DynamicPayload, SubmittableExtrinsic, TxProgress, TxStatus};
+use subxt::utils::{AccountId32, H256};
+use subxt::{OnlineClient, PolkadotConfig};
+use subxt_signer::sr25519::Keypair;
+
+/// Wait between two looks at the finalized head of the network
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(6);
+
+// ============================================================================
+// Substrate ( sr25519 relayer ) transaction submission
+
+/// Submits the transfers on behalf of their senders as `Proxy::proxy` extrinsics signed by the relayer account
+/// every sender must have added the relayer as its proxy, the relayer only pays the fees
+pub struct SubstrateRouter {
+    network: BlockchainNetwork,
+    api: OnlineClient<PolkadotConfig>,
+    rpc: RpcClient,
+    relayer: Keypair,
+    // Relayer nonces shared by the routers of every network
+    nonces: Arc<NonceManager>,
+}
+
+impl SubstrateRouter {
+    /// Router submitting to the `network` node at `url`
+    pub async fn connect(
+        network: BlockchainNetwork,
+        url: &str,
+        relayer: Keypair,
+        nonces: Arc<NonceManager>,
+    ) -> anyhow::Result<Self> {
+        if network.family() != NetworkFamily::Substrate {
+            anyhow::bail!("{network:?} is not a Substrate network");
+        }
+        let rpc = RpcClient::from_url(url).await?;
+        let api = OnlineClient::<PolkadotConfig>::from_rpc_client(rpc.clone()).await?;
+        Ok(Self {
+            network,
+            api,
+            rpc,
+            relayer,
+            nonces,
+        })
+    }
+
+    /// Transfer of the `tx` amount from the sender to the receiver
+    fn transfer(&self, tx: &TxSimulationObject) -> anyhow::Result<DynamicPayload> {
+        if tx.get_network() != self.network {
+            anyhow::bail!(
+                "{:?} transaction routed to {:?}",
+                tx.get_network(),
+                self.network
+            );
+        }
+        proxied_transfer(tx)
+    }
+}
+
+#[async_trait::async_trait]
+impl NetworkRouter for SubstrateRouter {
+    async fn submit(
+        &self,
+        tx: &TxSimulationObject,
+        record: &RecordSubmission<'_>,
+    ) -> Result<Box<dyn SubmissionTracker>, SubmitError> {
+        let transfer = self.transfer(tx).map_err(SubmitError::Rejected)?;
+        let relayer = self.relayer.public_key().0;
+        let from_block = self
+            .api
+            .blocks()
+            .at_latest()
+            .await
+            .map_err(anyhow::Error::from)?;
+        let from_block = u64::from(from_block.number());
+        // the relayer nonce may be stale by now, sign with a fresh one
+        let progress = self
+            .nonces
+            .submit_with_nonce(&self.network, &relayer, self, |nonce| {
+                let transfer = &transfer;
+                async move {
+                    let params = PolkadotExtrinsicParamsBuilder::new().nonce(nonce).build();
+                    let extrinsic = self
+                        .api
+                        .tx()
+                        .create_signed(transfer, &self.relayer, params)
+                        .await?;
+                    // a run stopped from here on leaves the transaction to be looked up
+                    let submission = Submission {
+                        tx_id: tx.get_tx_id(),
+                        network: self.network.clone(),
+                        nonce,
+                        tx_hash: format!("{:?}", extrinsic.hash()),
+                        extrinsic: extrinsic.encoded().to_vec(),
+                        from_block,
+                    };
+                    record(&submission)?;
+                    Ok(extrinsic.submit_and_watch().await?)
+                }
+            })
+            .await
+            .map_err(submission_error)?;
+        Ok(Box::new(SubstrateTracker { progress }))
+    }
+
+    async fn resume(
+        &self,
+        submission: &Submission,
+    ) -> Result<Option<Box<dyn SubmissionTracker>>, SubmitError> {
+        let mut tracker = InclusionTracker {
+            api: self.api.clone(),
+            rpc: self.rpc.clone(),
+            relayer: self.relayer.public_key().0,
+            submission: submission.clone(),
+            status: None,
+            done: false,
+        };
+        // the nonce is used, by this transaction or by another one of the relayer
+        let (number, used) = tracker.finalized().await?;
+        if used {
+            return match tracker.included_status(number).await? {
+                TxExecutionStatus::Usurped { .. } => Ok(None),
+                status => {
+                    tracker.status = Some(status);
+                    Ok(Some(Box::new(tracker)))
+                }
+            };
+        }
+        // the same signed bytes, never a second transfer
+        let extrinsic =
+            SubmittableExtrinsic::from_bytes(self.api.clone(), submission.extrinsic.clone());
+        match extrinsic.submit_and_watch().await {
+            Ok(progress) => Ok(Some(Box::new(SubstrateTracker { progress }))),
+            Err(err) => {
+                let err = anyhow::Error::from(err);
+                // still in the transaction pool or included meanwhile
+                if err.to_string().to_lowercase().contains("already imported")
+                    || NonceFault::classify(&err) == Some(NonceFault::Stale)
+                {
+                    Ok(Some(Box::new(tracker)))
+                } else {
+                    Err(submission_error(err))
+                }
+            }
+        }
+    }
+}
+
+/// Split the node submission errors into the permanent and the retryable ones
+/// the transaction pool reports invalid transactions as `Invalid Transaction` ( 1010 )
+/// nonce faults left once the retries ran out are retried on redelivery
+fn submission_error(err: anyhow::Error) -> SubmitError {
+    if NonceFault::classify(&err).is_none() && err.to_string().contains("Invalid Transaction") {
+        SubmitError::Rejected(err)
+    } else {
+        SubmitError::Unavailable(err)
+    }
+}
+
+#[async_trait::async_trait]
+impl NonceSource for SubstrateRouter {
+    async fn account_nonce(&self, account: &[u8]) -> anyhow::Result<u64> {
+        let account = AccountId32(account.try_into()?);
+        Ok(self.api.tx().account_nonce(&account).await?)
+    }
+}
+
+/// Follows a submitted extrinsic through subxt `TxProgress`
+pub struct SubstrateTracker {
+    progress: TxProgress<PolkadotConfig, OnlineClient<PolkadotConfig>>,
+}
+
+#[async_trait::async_trait]
+impl SubmissionTracker for SubstrateTracker {
+    fn tx_hash(&self) -> String {
+        format!("{:?}", self.progress.extrinsic_hash())
+    }
+
+    async fn next_status(&mut self) -> Option<anyhow::Result<TxExecutionStatus>> {
+        let tx_hash = self.tx_hash();
+        loop {
+            let status = match self.progress.next().await? {
+                Ok(status) => status,
+                Err(err) => return Some(Err(err.into())),
+            };
+            let status = match status {
+                // still on its way to a block
+                TxStatus::Validated | TxStatus::Broadcasted { .. } => continue,
+                TxStatus::NoLongerInBestBlock => Ok(TxExecutionStatus::Retracted { tx_hash }),
+                // the extrinsic index is best effort, the finalized status follows regardless
+                TxStatus::InBestBlock(in_block) => match in_block.fetch_events().await {
+                    Ok(events) => Ok(TxExecutionStatus::InBlock {
+                        tx_hash,
+                        block_hash: format!("{:?}", in_block.block_hash()),
+                        extrinsic_index: events.extrinsic_index(),
+                    }),
+                    Err(err) => {
+                        tracing::warn!(
+                            "failed to fetch the events of {tx_hash} in {:?}: {err}",
+                            in_block.block_hash()
+                        );
+                        continue;
+                    }
+                },
+                // included for good, the transfer may still have failed on dispatch
+                TxStatus::InFinalizedBlock(in_block) => match in_block.wait_for_success().await {
+                    Ok(events) => match proxied_call_error(&events) {
+                        Ok(None) => Ok(TxExecutionStatus::Finalized {
+                            tx_hash,
+                            block_hash: format!("{:?}", in_block.block_hash()),
+                            extrinsic_index: events.extrinsic_index(),
+                        }),
+                        Ok(Some(reason)) => Ok(TxExecutionStatus::Failed { reason }),
+                        Err(err) => Err(err),
+                    },
+                    Err(err) => Ok(TxExecutionStatus::Failed {
+                        reason: err.to_string(),
+                    }),
+                },
+                // the legacy rpc reports usurped transactions as invalid
+                TxStatus::Invalid { message } if message.contains("usurped") => {
+                    Ok(TxExecutionStatus::Usurped { tx_hash })
+                }
+                TxStatus::Invalid { message } => Ok(TxExecutionStatus::Invalid {
+                    tx_hash,
+                    reason: message,
+                }),
+                TxStatus::Dropped { message } => Ok(TxExecutionStatus::Dropped {
+                    tx_hash,
+                    reason: message,
+                }),
+                TxStatus::Error { message } => Ok(TxExecutionStatus::Failed { reason: message }),
+            };
+            return Some(status);
+        }
+    }
+}
+
+/// Follows a transaction whose submission progress was lost through the relayer nonce
+/// the finalized block using the nonce holds either the transaction or the one that usurped it
+pub struct InclusionTracker {
+    api: OnlineClient<PolkadotConfig>,
+    rpc: RpcClient,
+    relayer: [u8; 32],
+    submission: Submission,
+    // Status found before the tracker was handed out
+    status: Option<TxExecutionStatus>,
+    done: bool,
+}
+
+impl InclusionTracker {
+    /// Latest finalized block and whether the submission nonce is used in its state
+    async fn finalized(&self) -> anyhow::Result<(u64, bool)> {
+        let number = u64::from(self.api.blocks().at_latest().await?.number());
+        Ok((number, self.nonce_used_at(number).await?))
+    }
+
+    async fn block_hash(&self, number: u64) -> anyhow::Result<H256> {
+        let hash: Option<H256> = self
+            .rpc
+            .request("chain_getBlockHash", rpc_params![number])
+            .await?;
+        hash.ok_or_else(|| anyhow::anyhow!("No block {number}"))
+    }
+
+    async fn nonce_used_at(&self, number: u64) -> anyhow::Result<bool> {
+        let query =
+            subxt::dynamic::storage("System", "Account", vec![Value::from_bytes(self.relayer)]);
+        let storage = self.api.storage().at(self.block_hash(number).await?);
+        let nonce = match storage.fetch(&query).await? {
+            Some(info) => info
+                .to_value()?
+                .at("nonce")
+                .and_then(|nonce| nonce.as_u128())
+                .ok_or_else(|| anyhow::anyhow!("Unexpected System.Account layout"))?,
+            // accounts without any balance are not stored
+            None => 0,
+        };
+        Ok(nonce > u128::from(self.submission.nonce))
+    }
+
+    /// Final status of the transaction, the submission nonce being used by the finalized block `to`
+    async fn included_status(&self, to: u64) -> anyhow::Result<TxExecutionStatus> {
+        let tx_hash = self.submission.tx_hash.clone();
+        let number = first_block(self.submission.from_block, to, |number| {
+            self.nonce_used_at(number)
+        })
+        .await?;
+        let block = self.api.blocks().at(self.block_hash(number).await?).await?;
+        // block bodies hold the extrinsics without their length prefix
+        let submitted = Vec::<u8>::decode(&mut &self.submission.extrinsic[..])?;
+        for extrinsic in block.extrinsics().await?.iter() {
+            let extrinsic = extrinsic?;
+            if extrinsic.bytes() != submitted.as_slice() {
+                continue;
+            }
+            let events = extrinsic.events().await?;
+            for event in events.iter() {
+                let event = event?;
+                if event.pallet_name() == "System" && event.variant_name() == "ExtrinsicFailed" {
+                    let reason =
+                        DispatchError::decode_from(event.field_bytes(), self.api.metadata())
+                            .map(|err| err.to_string())
+                            .unwrap_or_else(|_| "System.ExtrinsicFailed".to_string());
+                    return Ok(TxExecutionStatus::Failed { reason });
+                }
+            }
+            if let Some(reason) = proxied_call_error(&events)? {
+                return Ok(TxExecutionStatus::Failed { reason });
+            }
+            return Ok(TxExecutionStatus::Finalized {
+                tx_hash,
+                block_hash: format!("{:?}", block.hash()),
+                extrinsic_index: extrinsic.index(),
+            });
+        }
+        Ok(TxExecutionStatus::Usurped { tx_hash })
+    }
+}
+
+#[async_trait::async_trait]
+impl SubmissionTracker for InclusionTracker {
+    fn tx_hash(&self) -> String {
+        self.submission.tx_hash.clone()
+    }
+
+    async fn next_status(&mut self) -> Option<anyhow::Result<TxExecutionStatus>> {
+        if self.done {
+            return None;
+        }
+        if let Some(status) = self.status.take() {
+            self.done = true;
+            return Some(Ok(status));
+        }
+        loop {
+            // the node being unreachable for a while says nothing about the transaction
+            match self.finalized().await {
+                Ok((number, true)) => match self.included_status(number).await {
+                    Ok(status) => {
+                        self.done = true;
+                        return Some(Ok(status));
+                    }
+                    Err(err) => tracing::warn!(
+                        "failed to look up transaction {}: {err}",
+                        self.submission.tx_hash
+                    ),
+                },
+                Ok((_, false)) => {}
+                Err(err) => tracing::warn!("failed to fetch the finalized nonce: {err}"),
+            }
+            tokio::time::sleep(BLOCK_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Lowest block in `from..=to` for which `used` holds, `used` holding for `to`
+/// and every block after the first one it holds for
+async fn first_block<F, Fut>(mut from: u64, mut to: u64, used: F) -> anyhow::Result<u64>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = anyhow::Result<bool>>,
+{
+    while from < to {
+        let middle = from + (to - from) / 2;
+        if used(middle).await? {
+            to = middle;
+        } else {
+            from = middle + 1;
+        }
+    }
+    Ok(to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_transactions_are_rejected() {
+        let err = anyhow::anyhow!(
+            "Rpc error: ErrorObject {{ code: ServerError(1010), message: \"Invalid Transaction\", \
+             data: Some(\"Inability to pay some fees (e.g. account balance too low)\") }}"
+        );
+        assert!(matches!(submission_error(err), SubmitError::Rejected(_)));
+    }
+
+    #[test]
+    fn nonce_faults_and_connection_errors_are_retried() {
+        let outdated = anyhow::anyhow!(
+            "Rpc error: ErrorObject {{ code: ServerError(1010), message: \"Invalid Transaction\", \
+             data: Some(\"Transaction is outdated\") }}"
+        );
+        assert!(matches!(
+            submission_error(outdated),
+            SubmitError::Unavailable(_)
+        ));
+        let closed = anyhow::anyhow!("Rpc error: The background task been terminated");
+        assert!(matches!(
+            submission_error(closed),
+            SubmitError::Unavailable(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn first_block_using_the_nonce_is_found() {
+        // the nonce is used from block 137 on
+        let used = |number: u64| async move { Ok(number >= 137) };
+
+        assert_eq!(first_block(100, 200, used).await.unwrap(), 137);
+        assert_eq!(first_block(137, 137, used).await.unwrap(), 137);
+        assert_eq!(first_block(136, 137, used).await.unwrap(), 137);
+    }
+}
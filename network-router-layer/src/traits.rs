@@ -0,0 +1,114 @@
+use futures::stream::BoxStream;
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use parity_scale_codec::{Decode, Encode};
+use primitives::{
+    BlockchainNetwork, TxExecutionStatus, TxId, TxSimulationDelivery, TxSimulationObject,
+};
+
+/// Methods of the address verification layer used by the router
+/// a client only mirror of the av-layer `Router` rpc, served on its authenticated router endpoint
+#[rpc(client)]
+pub trait AvLayer {
+    /// Feed of the transactions confirmed by both parties
+    /// each `TxSimulationDelivery` must be acknowledged with `ackConfirmedTx`
+    #[subscription(name = "receiveConfirmedTx", unsubscribe = "unsubReceiveConfirmedTx", item=TxSimulationDelivery)]
+    async fn receive_confirmed_tx(&self) -> SubscriptionResult;
+
+    #[method(name = "ackConfirmedTx")]
+    async fn ack_confirmed_tx(&self, delivery_id: u64) -> RpcResult<()>;
+
+    #[method(name = "reportTxStatus")]
+    async fn report_tx_status(&self, tx_id: TxId, status: TxExecutionStatus) -> RpcResult<()>;
+}
+
+/// Calls of the router to the address verification layer
+/// made over the `AvLayerConnection` websocket, mocked in tests
+#[async_trait::async_trait]
+pub trait AvLayerApi: Send + Sync {
+    /// Feed of the confirmed transactions, ends once the connection is lost
+    async fn confirmed_txs(
+        &self,
+    ) -> anyhow::Result<BoxStream<'static, anyhow::Result<TxSimulationDelivery>>>;
+
+    async fn ack_confirmed_tx(&self, delivery_id: u64) -> anyhow::Result<()>;
+
+    async fn report_tx_status(&self, tx_id: TxId, status: TxExecutionStatus) -> anyhow::Result<()>;
+}
+
+/// Signs and submits the confirmed transactions of a network
+/// implemented per chain family ( Substrate | EVM | Solana ) and dispatched to by `RouterRegistry`
+#[async_trait::async_trait]
+pub trait NetworkRouter: Send + Sync {
+    /// Sign `tx` with the relayer account and submit it to the network
+    /// every signed transaction is handed to `record` before it is submitted
+    /// returns once the network accepted the transaction
+    async fn submit(
+        &self,
+        tx: &TxSimulationObject,
+        record: &RecordSubmission<'_>,
+    ) -> Result<Box<dyn SubmissionTracker>, SubmitError>;
+
+    /// Follow up on a transaction signed by a run stopped while submitting it
+    /// submitted again only while its nonce is still unused on the network
+    /// `None` once another transaction used the nonce, the transfer is then safe to sign again
+    async fn resume(
+        &self,
+        submission: &Submission,
+    ) -> Result<Option<Box<dyn SubmissionTracker>>, SubmitError>;
+}
+
+/// Persists a `Submission` before it is sent to the network
+pub type RecordSubmission<'a> = dyn Fn(&Submission) -> anyhow::Result<()> + Send + Sync + 'a;
+
+/// Signed transaction recorded before it is submitted
+/// so a later run checks the network for it instead of signing the transfer again
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct Submission {
+    pub tx_id: TxId,
+    pub network: BlockchainNetwork,
+    /// Nonce of the relayer the transaction is signed with
+    pub nonce: u64,
+    pub tx_hash: String,
+    /// Encoded signed transaction, submitted as is
+    pub extrinsic: Vec<u8>,
+    /// Latest finalized block when signed, the transaction is included after it
+    pub from_block: u64,
+}
+
+/// Reason a transaction could not be submitted to its network
+#[derive(Debug)]
+pub enum SubmitError {
+    /// The network refused the transaction, submitting it again fails the same way
+    Rejected(anyhow::Error),
+    /// The network could not be reached or asked to retry later
+    Unavailable(anyhow::Error),
+}
+
+impl std::fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubmitError::Rejected(err) => write!(f, "Transaction rejected: {err}"),
+            SubmitError::Unavailable(err) => write!(f, "Network unavailable: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for SubmitError {}
+
+// an error of unknown origin is retried rather than failing the transfer for good
+impl From<anyhow::Error> for SubmitError {
+    fn from(err: anyhow::Error) -> Self {
+        SubmitError::Unavailable(err)
+    }
+}
+
+/// Tracking handle of a transaction submitted to its network
+#[async_trait::async_trait]
+pub trait SubmissionTracker: Send {
+    /// Hash of the submitted transaction on its network
+    fn tx_hash(&self) -> String;
+
+    /// Next status of the transaction on the network
+    /// `None` once the network stopped reporting on the transaction
+    async fn next_status(&mut self) -> Option<anyhow::Result<TxExecutionStatus>>;
+}
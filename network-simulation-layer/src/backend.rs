@@ -0,0 +1,41 @@
+use crate::report::{ExecutionOutcome, SimulatedEvent};
+use primitives::{BlockchainNetwork, TxSimulationObject, VaneMultiAddress};
+use subxt::utils::AccountId32;
+
+/// Source of forked network states, a local node or a mock runtime
+#[async_trait::async_trait]
+pub trait SimulationBackend: Send + Sync {
+    /// Fresh copy of the `network` state, whatever is executed on it never reaches the network
+    async fn fork(&self, network: &BlockchainNetwork) -> anyhow::Result<Box<dyn ForkedState>>;
+}
+
+/// Network state a transaction is executed against
+#[async_trait::async_trait]
+pub trait ForkedState: Send + Sync {
+    /// Free balance of `account` in the smallest unit of the network
+    async fn free_balance(
+        &self,
+        account: &VaneMultiAddress<AccountId32, ()>,
+    ) -> anyhow::Result<u128>;
+
+    /// Execute `tx` as sent by `origin`
+    /// returns `Err` only when the fork could not execute it, a failed transaction is an `Execution`
+    async fn execute(
+        &mut self,
+        origin: &VaneMultiAddress<AccountId32, ()>,
+        tx: &TxSimulationObject,
+    ) -> anyhow::Result<Execution>;
+
+    /// Undo everything executed on the fork so the next simulation starts from the same state
+    /// called once per simulation, whether the execution succeeded or not
+    async fn discard(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Result of a transaction executed on a fork
+#[derive(Debug, Clone)]
+pub struct Execution {
+    pub outcome: ExecutionOutcome,
+    pub events: Vec<SimulatedEvent>,
+}
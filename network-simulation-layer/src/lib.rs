@@ -0,0 +1,13 @@
+//! Network simulation layer
+//! Executes confirmed transactions against a forked copy of the network state
+//! and reports the outcome before the network router layer touches the real chain
+
+pub mod backend;
+pub mod mock;
+pub mod report;
+pub mod simulator;
+pub mod substrate;
+
+pub use backend::{Execution, ForkedState, SimulationBackend};
+pub use report::{BalanceDelta, ExecutionOutcome, ExecutionReport, SimulatedEvent};
+pub use simulator::Simulator;
@@ -0,0 +1,108 @@
+use crate::backend::{Execution, ForkedState, SimulationBackend};
+use crate::report::{ExecutionOutcome, SimulatedEvent};
+use primitives::{BlockchainNetwork, TxSimulationObject, VaneMultiAddress};
+use std::collections::{BTreeMap, HashMap};
+use subxt::utils::AccountId32;
+
+type Balances = BTreeMap<VaneMultiAddress<AccountId32, ()>, u128>;
+
+/// Mock runtime keeping the free balances of every network in memory
+/// transfers are charged a flat `fee` and fail when the origin can not pay the amount and the fee
+#[derive(Debug, Clone, Default)]
+pub struct MockBackend {
+    pub fee: u128,
+    balances: HashMap<BlockchainNetwork, Balances>,
+}
+
+impl MockBackend {
+    pub fn new(fee: u128) -> Self {
+        Self {
+            fee,
+            balances: HashMap::new(),
+        }
+    }
+
+    pub fn set_balance(
+        &mut self,
+        network: BlockchainNetwork,
+        account: VaneMultiAddress<AccountId32, ()>,
+        balance: u128,
+    ) {
+        self.balances
+            .entry(network)
+            .or_default()
+            .insert(account, balance);
+    }
+
+    pub fn balance(
+        &self,
+        network: &BlockchainNetwork,
+        account: &VaneMultiAddress<AccountId32, ()>,
+    ) -> u128 {
+        self.balances
+            .get(network)
+            .and_then(|balances| balances.get(account))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait::async_trait]
+impl SimulationBackend for MockBackend {
+    async fn fork(&self, network: &BlockchainNetwork) -> anyhow::Result<Box<dyn ForkedState>> {
+        Ok(Box::new(MockFork {
+            fee: self.fee,
+            balances: self.balances.get(network).cloned().unwrap_or_default(),
+        }))
+    }
+}
+
+/// Copy of the mock balances of one network
+pub struct MockFork {
+    fee: u128,
+    balances: Balances,
+}
+
+#[async_trait::async_trait]
+impl ForkedState for MockFork {
+    async fn free_balance(
+        &self,
+        account: &VaneMultiAddress<AccountId32, ()>,
+    ) -> anyhow::Result<u128> {
+        Ok(self.balances.get(account).copied().unwrap_or_default())
+    }
+
+    async fn execute(
+        &mut self,
+        origin: &VaneMultiAddress<AccountId32, ()>,
+        tx: &TxSimulationObject,
+    ) -> anyhow::Result<Execution> {
+        let amount = tx.get_call().amount();
+        let origin_balance = self.free_balance(origin).await?;
+        if origin_balance < self.fee {
+            anyhow::bail!("Origin can not pay the transaction fee");
+        }
+        self.balances
+            .insert(origin.clone(), origin_balance - self.fee);
+        let mut events = vec![SimulatedEvent::new(
+            "TransactionPayment",
+            "TransactionFeePaid",
+        )];
+
+        let outcome = if origin_balance - self.fee < amount {
+            events.push(SimulatedEvent::new("System", "ExtrinsicFailed"));
+            ExecutionOutcome::Failed("Balances.InsufficientBalance".to_string())
+        } else {
+            let receiver = tx.get_receiver_address();
+            *self
+                .balances
+                .get_mut(origin)
+                .expect("origin balance set above") -= amount;
+            *self.balances.entry(receiver).or_default() += amount;
+            events.push(SimulatedEvent::new("Balances", "Transfer"));
+            events.push(SimulatedEvent::new("System", "ExtrinsicSuccess"));
+            ExecutionOutcome::Success
+        };
+        Ok(Execution { outcome, events })
+    }
+}
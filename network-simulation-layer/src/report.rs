@@ -0,0 +1,60 @@
+use primitives::{BlockchainNetwork, TxId, VaneMultiAddress};
+use serde::{Deserialize, Serialize};
+use subxt::utils::AccountId32;
+
+/// Outcome of a transaction executed on a fork of its network
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExecutionReport {
+    pub tx_id: TxId,
+    pub network: BlockchainNetwork,
+    pub outcome: ExecutionOutcome,
+    /// Balance of the origin and the receiver around the execution
+    pub balance_deltas: Vec<BalanceDelta>,
+    /// Fee paid by the origin
+    pub fee: u128,
+    pub events: Vec<SimulatedEvent>,
+}
+
+impl ExecutionReport {
+    pub fn is_success(&self) -> bool {
+        self.outcome == ExecutionOutcome::Success
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExecutionOutcome {
+    Success,
+    /// The transaction was included but failed, e.g `Balances.InsufficientBalance`
+    Failed(String),
+}
+
+/// Free balance of an account before and after the execution
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BalanceDelta {
+    pub address: VaneMultiAddress<AccountId32, ()>,
+    pub before: u128,
+    pub after: u128,
+}
+
+impl BalanceDelta {
+    /// Signed change of the balance
+    pub fn delta(&self) -> i128 {
+        self.after as i128 - self.before as i128
+    }
+}
+
+/// Event emitted by the execution, named as `pallet.variant` on Substrate
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SimulatedEvent {
+    pub pallet: String,
+    pub variant: String,
+}
+
+impl SimulatedEvent {
+    pub fn new(pallet: impl Into<String>, variant: impl Into<String>) -> Self {
+        Self {
+            pallet: pallet.into(),
+            variant: variant.into(),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use crate::backend::{ForkedState, SimulationBackend};
+use crate::report::{BalanceDelta, ExecutionOutcome, ExecutionReport};
+use primitives::{TxSimulationObject, VaneMultiAddress};
+use subxt::utils::AccountId32;
+
+/// Runs confirmed transactions on a fork of their network through the `SimulationBackend`
+pub struct Simulator<B> {
+    backend: B,
+}
+
+impl<B: SimulationBackend> Simulator<B> {
+    pub fn new(backend: B) -> Self {
+        Self { backend }
+    }
+
+    /// Execute `tx` sent by `origin` on a fresh fork of its network
+    pub async fn simulate(
+        &self,
+        origin: &VaneMultiAddress<AccountId32, ()>,
+        tx: &TxSimulationObject,
+    ) -> anyhow::Result<ExecutionReport> {
+        let mut fork = self.backend.fork(&tx.get_network()).await?;
+        let report = Self::execute(fork.as_mut(), origin, tx).await;
+        // a fork left as is would hand the executed transfer to the next simulation
+        fork.discard()
+            .await
+            .map_err(|err| err.context("Failed to discard the fork"))?;
+        report
+    }
+
+    async fn execute(
+        fork: &mut dyn ForkedState,
+        origin: &VaneMultiAddress<AccountId32, ()>,
+        tx: &TxSimulationObject,
+    ) -> anyhow::Result<ExecutionReport> {
+        let network = tx.get_network();
+        let receiver = tx.get_receiver_address();
+        let origin_before = fork.free_balance(origin).await?;
+        let receiver_before = fork.free_balance(&receiver).await?;
+        let execution = fork.execute(origin, tx).await?;
+        let origin_after = fork.free_balance(origin).await?;
+        let receiver_after = fork.free_balance(&receiver).await?;
+
+        // whatever the origin lost beyond the transferred amount went to fees
+        let transferred = match execution.outcome {
+            ExecutionOutcome::Success => tx.get_call().amount(),
+            ExecutionOutcome::Failed(_) => 0,
+        };
+        let fee = origin_before
+            .saturating_sub(origin_after)
+            .saturating_sub(transferred);
+
+        let report = ExecutionReport {
+            tx_id: tx.get_tx_id(),
+            network,
+            outcome: execution.outcome,
+            balance_deltas: vec![
+                BalanceDelta {
+                    address: origin.clone(),
+                    before: origin_before,
+                    after: origin_after,
+                },
+                BalanceDelta {
+                    address: receiver,
+                    before: receiver_before,
+                    after: receiver_after,
+                },
+            ],
+            fee,
+            events: execution.events,
+        };
+        tracing::info!(
+            "simulated transaction {}: {:?}",
+            report.tx_id,
+            report.outcome
+        );
+        Ok(report)
+    }
+}
@@ -0,0 +1,208 @@
+use crate::backend::{Execution, ForkedState, SimulationBackend};
+use crate::report::{ExecutionOutcome, SimulatedEvent};
+use primitives::{BlockchainNetwork, NetworkFamily, TxSimulationObject, VaneMultiAddress};
+use std::{collections::HashMap, sync::Arc};
+use subxt::backend::rpc::{rpc_params, RawValue, RpcClient};
+use subxt::blocks::ExtrinsicEvents;
+use subxt::dynamic::Value;
+use subxt::ext::scale_value::{At, ValueDef};
+use subxt::tx::DynamicPayload;
+use subxt::utils::AccountId32;
+use subxt::{OnlineClient, PolkadotConfig};
+use subxt_signer::sr25519::Keypair;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+// ============================================================================
+// Substrate transfers, dispatched on behalf of the sender by the relayer
+
+/// Transfer of the `tx` amount from its sender to its receiver, signed and paid for by the relayer
+/// the sender must have added the relayer as its `Any` proxy for `Proxy::proxy` to dispatch it
+pub fn proxied_transfer(tx: &TxSimulationObject) -> anyhow::Result<DynamicPayload> {
+    transfer_on_behalf_of(&tx.get_sender_address(), tx)
+}
+
+/// Transfer of the `tx` amount from `real` to the receiver, dispatched by a proxy of `real`
+fn transfer_on_behalf_of(
+    real: &VaneMultiAddress<AccountId32, ()>,
+    tx: &TxSimulationObject,
+) -> anyhow::Result<DynamicPayload> {
+    let real = real.account_id_32()?;
+    let receiver = tx.get_receiver_address().account_id_32()?;
+    let transfer = Value::unnamed_variant(
+        "Balances",
+        [Value::unnamed_variant(
+            "transfer_keep_alive",
+            [
+                Value::unnamed_variant("Id", [Value::from_bytes(receiver)]),
+                Value::u128(tx.get_call().amount()),
+            ],
+        )],
+    );
+    Ok(subxt::dynamic::tx(
+        "Proxy",
+        "proxy",
+        vec![
+            Value::unnamed_variant("Id", [Value::from_bytes(real)]),
+            // whichever proxy type the sender gave the relayer
+            Value::unnamed_variant("None", []),
+            transfer,
+        ],
+    ))
+}
+
+/// Error of the transfer dispatched by `Proxy::proxy`, found in the events of the proxy extrinsic
+/// which succeeds whether the transfer did or not
+pub fn proxied_call_error(
+    events: &ExtrinsicEvents<PolkadotConfig>,
+) -> anyhow::Result<Option<String>> {
+    for event in events.iter() {
+        let event = event?;
+        if event.pallet_name() != "Proxy" || event.variant_name() != "ProxyExecuted" {
+            continue;
+        }
+        let fields = event.field_values()?;
+        if let Some(ValueDef::Variant(result)) = fields.at("result").map(|result| &result.value) {
+            if result.name == "Err" {
+                return Ok(Some(format!("Proxied transfer failed: {}", result.values)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// ============================================================================
+// Substrate fork execution, against a chopsticks fork
+
+/// Executes transactions on Substrate nodes forked from the real networks
+/// every `url` must point to a chopsticks fork, e.g `npx @acala-network/chopsticks --endpoint <network rpc>`
+/// the transfers are really submitted to it and rolled back with `dev_setHead` afterwards
+#[derive(Default)]
+pub struct SubstrateForkBackend {
+    nodes: HashMap<BlockchainNetwork, ForkNode>,
+}
+
+struct ForkNode {
+    url: String,
+    relayer: Keypair,
+    // One simulation at a time per fork node, each runs from the head the previous one restored
+    turn: Arc<Mutex<()>>,
+}
+
+impl SubstrateForkBackend {
+    /// Fork of `network` served at `url`
+    /// the simulated transfers are dispatched from their origin by the `relayer` proxy, as the router does
+    pub fn with_node(
+        mut self,
+        network: BlockchainNetwork,
+        url: impl Into<String>,
+        relayer: Keypair,
+    ) -> anyhow::Result<Self> {
+        if network.family() != NetworkFamily::Substrate {
+            anyhow::bail!("{network:?} is not a Substrate network");
+        }
+        let node = ForkNode {
+            url: url.into(),
+            relayer,
+            turn: Arc::default(),
+        };
+        self.nodes.insert(network, node);
+        Ok(self)
+    }
+}
+
+#[async_trait::async_trait]
+impl SimulationBackend for SubstrateForkBackend {
+    async fn fork(&self, network: &BlockchainNetwork) -> anyhow::Result<Box<dyn ForkedState>> {
+        let node = self
+            .nodes
+            .get(network)
+            .ok_or_else(|| anyhow::anyhow!("No fork node for {network:?}"))?;
+        let turn = node.turn.clone().lock_owned().await;
+        let rpc = RpcClient::from_url(&node.url).await?;
+        let api = OnlineClient::<PolkadotConfig>::from_rpc_client(rpc.clone()).await?;
+        let head: String = rpc.request("chain_getBlockHash", rpc_params![]).await?;
+        Ok(Box::new(SubstrateFork {
+            api,
+            rpc,
+            relayer: node.relayer.clone(),
+            head,
+            _turn: turn,
+        }))
+    }
+}
+
+/// Connection to one fork node, held by a single simulation until dropped
+pub struct SubstrateFork {
+    api: OnlineClient<PolkadotConfig>,
+    rpc: RpcClient,
+    relayer: Keypair,
+    // Best block of the fork before the simulation
+    head: String,
+    _turn: OwnedMutexGuard<()>,
+}
+
+#[async_trait::async_trait]
+impl ForkedState for SubstrateFork {
+    async fn free_balance(
+        &self,
+        account: &VaneMultiAddress<AccountId32, ()>,
+    ) -> anyhow::Result<u128> {
+        let query = subxt::dynamic::storage(
+            "System",
+            "Account",
+            vec![Value::from_bytes(account.account_id_32()?)],
+        );
+        let Some(info) = self.api.storage().at_latest().await?.fetch(&query).await? else {
+            // accounts without any balance are not stored
+            return Ok(0);
+        };
+        info.to_value()?
+            .at("data")
+            .at("free")
+            .and_then(|free| free.as_u128())
+            .ok_or_else(|| anyhow::anyhow!("Unexpected System.Account layout"))
+    }
+
+    async fn execute(
+        &mut self,
+        origin: &VaneMultiAddress<AccountId32, ()>,
+        tx: &TxSimulationObject,
+    ) -> anyhow::Result<Execution> {
+        // the origin's funds move exactly as on the network, through the relayer's proxy call
+        let transfer = transfer_on_behalf_of(origin, tx)?;
+        let in_block = self
+            .api
+            .tx()
+            .sign_and_submit_then_watch_default(&transfer, &self.relayer)
+            .await?
+            .wait_for_finalized()
+            .await?;
+
+        let events = in_block
+            .fetch_events()
+            .await?
+            .iter()
+            .map(|event| {
+                event.map(|event| SimulatedEvent::new(event.pallet_name(), event.variant_name()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let outcome = match in_block.wait_for_success().await {
+            Ok(events) => match proxied_call_error(&events)? {
+                None => ExecutionOutcome::Success,
+                Some(reason) => ExecutionOutcome::Failed(reason),
+            },
+            // the dispatch error, e.g `Balances.InsufficientBalance`
+            Err(err) => ExecutionOutcome::Failed(err.to_string()),
+        };
+        Ok(Execution { outcome, events })
+    }
+
+    async fn discard(&mut self) -> anyhow::Result<()> {
+        // chopsticks drops every block built on top of the new head
+        let _: Box<RawValue> = self
+            .rpc
+            .request("dev_setHead", rpc_params![&self.head])
+            .await?;
+        Ok(())
+    }
+}
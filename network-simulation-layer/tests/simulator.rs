@@ -0,0 +1,160 @@
+//! Simulation of confirmed transactions against the mock runtime
+
+use network_simulation_layer::mock::MockBackend;
+use network_simulation_layer::{
+    Execution, ExecutionOutcome, ForkedState, SimulatedEvent, SimulationBackend, Simulator,
+};
+use primitives::{
+    BlockchainNetwork, TxConfirmationObject, TxObject, TxSimulationObject, VaneCallData,
+    VaneMultiAddress,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use subxt::utils::{AccountId32, MultiAddress};
+
+type Address = VaneMultiAddress<AccountId32, ()>;
+
+const FEE: u128 = 10;
+const ALICE: [u8; 32] = [1; 32];
+const BOB: [u8; 32] = [2; 32];
+
+fn transfer(amount: u128) -> TxSimulationObject {
+    let network = BlockchainNetwork::Polkadot;
+    let tx = TxObject::new(
+        VaneCallData::new(network.clone(), amount),
+        MultiAddress::Address32(ALICE),
+        MultiAddress::Address32(BOB),
+        network,
+    );
+    TxConfirmationObject::from(tx).into()
+}
+
+fn backend(alice: u128) -> MockBackend {
+    let mut backend = MockBackend::new(FEE);
+    backend.set_balance(
+        BlockchainNetwork::Polkadot,
+        Address::Address32(ALICE),
+        alice,
+    );
+    backend
+}
+
+#[tokio::test]
+async fn successful_transfer_reports_deltas_fee_and_events() {
+    let simulator = Simulator::new(backend(1_000));
+    let tx = transfer(400);
+
+    let report = simulator
+        .simulate(&Address::Address32(ALICE), &tx)
+        .await
+        .unwrap();
+
+    assert!(report.is_success());
+    assert_eq!(report.tx_id, tx.get_tx_id());
+    assert_eq!(report.fee, FEE);
+    let deltas: Vec<i128> = report
+        .balance_deltas
+        .iter()
+        .map(|delta| delta.delta())
+        .collect();
+    assert_eq!(deltas, vec![-410, 400]);
+    assert!(report
+        .events
+        .contains(&SimulatedEvent::new("Balances", "Transfer")));
+}
+
+#[tokio::test]
+async fn insufficient_balance_fails_without_moving_funds() {
+    let simulator = Simulator::new(backend(100));
+
+    let report = simulator
+        .simulate(&Address::Address32(ALICE), &transfer(400))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        report.outcome,
+        ExecutionOutcome::Failed("Balances.InsufficientBalance".to_string())
+    );
+    // the fee is still charged for the failed dispatch
+    assert_eq!(report.fee, FEE);
+    assert_eq!(report.balance_deltas[1].delta(), 0);
+}
+
+#[tokio::test]
+async fn simulation_leaves_the_base_state_untouched() {
+    let backend = backend(1_000);
+    let simulator = Simulator::new(backend.clone());
+
+    for _ in 0..2 {
+        let report = simulator
+            .simulate(&Address::Address32(ALICE), &transfer(400))
+            .await
+            .unwrap();
+        // every simulation starts from the same fork point
+        assert_eq!(report.balance_deltas[0].before, 1_000);
+    }
+    assert_eq!(
+        backend.balance(&BlockchainNetwork::Polkadot, &Address::Address32(ALICE)),
+        1_000
+    );
+}
+
+/// Mock backend counting the forks discarded after their simulation
+struct DiscardCounter {
+    backend: MockBackend,
+    discarded: Arc<AtomicUsize>,
+}
+
+struct CountedFork {
+    fork: Box<dyn ForkedState>,
+    discarded: Arc<AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl SimulationBackend for DiscardCounter {
+    async fn fork(&self, network: &BlockchainNetwork) -> anyhow::Result<Box<dyn ForkedState>> {
+        Ok(Box::new(CountedFork {
+            fork: self.backend.fork(network).await?,
+            discarded: self.discarded.clone(),
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl ForkedState for CountedFork {
+    async fn free_balance(&self, account: &Address) -> anyhow::Result<u128> {
+        self.fork.free_balance(account).await
+    }
+
+    async fn execute(
+        &mut self,
+        origin: &Address,
+        tx: &TxSimulationObject,
+    ) -> anyhow::Result<Execution> {
+        self.fork.execute(origin, tx).await
+    }
+
+    async fn discard(&mut self) -> anyhow::Result<()> {
+        self.discarded.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn every_simulation_discards_its_fork() {
+    let discarded = Arc::new(AtomicUsize::new(0));
+    let simulator = Simulator::new(DiscardCounter {
+        backend: backend(1_000),
+        discarded: discarded.clone(),
+    });
+    let alice = Address::Address32(ALICE);
+
+    simulator.simulate(&alice, &transfer(400)).await.unwrap();
+    simulator.simulate(&alice, &transfer(4_000)).await.unwrap();
+    // bob can not pay the fee, the fork refuses to execute the transfer
+    let bob = Address::Address32(BOB);
+    assert!(simulator.simulate(&bob, &transfer(400)).await.is_err());
+
+    assert_eq!(discarded.load(Ordering::SeqCst), 3);
+}
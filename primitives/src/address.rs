@@ -0,0 +1,197 @@
+//! Parsing and formatting of `VaneMultiAddress` in the native format of each network
+//! SS58 for Substrate networks, EIP-55 checksummed hex for EVM networks and base58 for Solana
+//! An address encoded for another network than the selected one is rejected
+
+use crate::common::{AddressError, BlockchainNetwork, VaneMultiAddress};
+use sp_core::crypto::{AccountId32 as SpAccountId32, Ss58AddressFormat, Ss58Codec};
+use sp_core::keccak_256;
+use subxt::utils::AccountId32;
+
+impl BlockchainNetwork {
+    /// SS58 prefix of the network accounts, `None` for networks not using SS58
+    pub fn ss58_prefix(&self) -> Option<u16> {
+        match self {
+            BlockchainNetwork::Polkadot => Some(0),
+            BlockchainNetwork::Kusama => Some(2),
+            BlockchainNetwork::Astar => Some(5),
+            BlockchainNetwork::Moonbeam => Some(1284),
+            _ => None,
+        }
+    }
+
+    /// Network using the SS58 `prefix`
+    pub fn from_ss58_prefix(prefix: u16) -> Option<Self> {
+        [
+            BlockchainNetwork::Polkadot,
+            BlockchainNetwork::Kusama,
+            BlockchainNetwork::Astar,
+            BlockchainNetwork::Moonbeam,
+        ]
+        .into_iter()
+        .find(|network| network.ss58_prefix() == Some(prefix))
+    }
+
+    /// Networks whose accounts are 20 bytes addresses shown as EIP-55 hex
+    pub fn uses_evm_accounts(&self) -> bool {
+        matches!(
+            self,
+            BlockchainNetwork::Moonbeam
+                | BlockchainNetwork::Ethereum
+                | BlockchainNetwork::Optimism
+                | BlockchainNetwork::Arbitrum
+        )
+    }
+}
+
+impl VaneMultiAddress<AccountId32, ()> {
+    /// Parse an address shown by wallets of the `network`
+    pub fn parse(address: &str, network: &BlockchainNetwork) -> Result<Self, AddressError> {
+        let address = address.trim();
+        if network.uses_evm_accounts() && address.starts_with("0x") {
+            return parse_evm(address).map(VaneMultiAddress::Address20);
+        }
+        if address.starts_with("0x") {
+            return Err(AddressError::NetworkMismatch(format!(
+                "hex address on {network:?}"
+            )));
+        }
+        if network.uses_evm_accounts() {
+            if SpAccountId32::from_ss58check_with_version(address).is_ok() {
+                return Err(AddressError::NetworkMismatch(format!(
+                    "SS58 address on {network:?}"
+                )));
+            }
+            return Err(AddressError::InvalidEncoding(format!(
+                "{network:?} addresses are 0x prefixed hex"
+            )));
+        }
+        if let Some(prefix) = network.ss58_prefix() {
+            return parse_ss58(address, prefix, network).map(VaneMultiAddress::Address32);
+        }
+        match network {
+            BlockchainNetwork::Solana => parse_base58(address).map(VaneMultiAddress::Address32),
+            _ => Err(AddressError::InvalidEncoding(format!(
+                "{network:?} addresses are 0x prefixed hex"
+            ))),
+        }
+    }
+
+    /// Render the address as shown by wallets of the `network`
+    /// fails when the address can not belong to the `network`
+    pub fn to_network_string(&self, network: &BlockchainNetwork) -> Result<String, AddressError> {
+        // `Raw` addresses may carry the address text itself
+        if let VaneMultiAddress::Raw(raw) = self {
+            if raw.len() != 20 && raw.len() != 32 {
+                let text = std::str::from_utf8(raw).map_err(|_| {
+                    AddressError::InvalidEncoding(
+                        "raw address is neither bytes nor text".to_string(),
+                    )
+                })?;
+                return Self::parse(text, network)?.to_network_string(network);
+            }
+        }
+        if network.uses_evm_accounts() {
+            // Moonbeam keeps an SS58 prefix but its accounts are 20 bytes
+            return match self.evm_address() {
+                Err(AddressError::InvalidLength { found: 32, .. }) => Err(
+                    AddressError::NetworkMismatch(format!("32 bytes address on {network:?}")),
+                ),
+                address => address.map(|address| to_checksum_hex(&address)),
+            };
+        }
+        if let VaneMultiAddress::Address20(_) = self {
+            return Err(AddressError::NetworkMismatch(format!(
+                "20 bytes address on {network:?}"
+            )));
+        }
+        let account = self.account_id_32()?;
+        if let Some(prefix) = network.ss58_prefix() {
+            return Ok(SpAccountId32::new(account)
+                .to_ss58check_with_version(Ss58AddressFormat::custom(prefix)));
+        }
+        match network {
+            BlockchainNetwork::Solana => Ok(bs58::encode(account).into_string()),
+            _ => Err(AddressError::NetworkMismatch(format!(
+                "32 bytes address on {network:?}"
+            ))),
+        }
+    }
+
+    /// Whether the address can belong to the `network`
+    pub fn check_network(&self, network: &BlockchainNetwork) -> Result<(), AddressError> {
+        self.to_network_string(network).map(|_| ())
+    }
+}
+
+fn parse_ss58(
+    address: &str,
+    prefix: u16,
+    network: &BlockchainNetwork,
+) -> Result<[u8; 32], AddressError> {
+    let (account, format) = SpAccountId32::from_ss58check_with_version(address)
+        .map_err(|err| AddressError::InvalidEncoding(format!("{err:?}")))?;
+    if u16::from(format) != prefix {
+        return Err(AddressError::NetworkMismatch(format!(
+            "SS58 prefix {} is not the {network:?} prefix {prefix}",
+            u16::from(format)
+        )));
+    }
+    Ok(account.into())
+}
+
+fn parse_base58(address: &str) -> Result<[u8; 32], AddressError> {
+    // SS58 is base58 too, point out the wrong network rather than a bad length
+    if SpAccountId32::from_ss58check_with_version(address).is_ok() {
+        return Err(AddressError::NetworkMismatch(
+            "SS58 address on Solana".to_string(),
+        ));
+    }
+    let bytes = bs58::decode(address)
+        .into_vec()
+        .map_err(|err| AddressError::InvalidEncoding(err.to_string()))?;
+    let found = bytes.len();
+    bytes.try_into().map_err(|_| AddressError::InvalidLength {
+        expected: 32,
+        found,
+    })
+}
+
+/// Parse a 0x prefixed hex address, mixed case addresses must carry a valid EIP-55 checksum
+fn parse_evm(address: &str) -> Result<[u8; 20], AddressError> {
+    let hex = &address[2..];
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AddressError::InvalidEncoding(
+            "expected 40 hex characters".to_string(),
+        ));
+    }
+    let mut bytes = [0u8; 20];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|err| AddressError::InvalidEncoding(err.to_string()))?;
+    }
+
+    let is_mixed_case =
+        hex.chars().any(|c| c.is_ascii_lowercase()) && hex.chars().any(|c| c.is_ascii_uppercase());
+    if is_mixed_case && to_checksum_hex(&bytes) != address {
+        return Err(AddressError::InvalidChecksum);
+    }
+    Ok(bytes)
+}
+
+/// EIP-55 mixed case checksum encoding
+fn to_checksum_hex(address: &[u8; 20]) -> String {
+    let lower = sp_core::bytes::to_hex(address, false);
+    let lower = &lower[2..];
+    let hash = keccak_256(lower.as_bytes());
+
+    let mut checksummed = String::from("0x");
+    for (i, c) in lower.chars().enumerate() {
+        let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+        if nibble >= 8 {
+            checksummed.push(c.to_ascii_uppercase());
+        } else {
+            checksummed.push(c);
+        }
+    }
+    checksummed
+}
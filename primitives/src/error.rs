@@ -0,0 +1,134 @@
+//! Errors returned by the address verification layer RPC
+//! Each variant keeps a stable JSON-RPC error code so clients can branch on the code
+//! while the variant itself is sent as the error `data`
+
+use crate::common::{BlockchainNetwork, ConfirmationStatus, NetworkFamily, TxId};
+use jsonrpsee::types::error::{CallError, ErrorObject, ErrorObjectOwned};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum VaneError {
+    /// No pending transaction with the `tx_id`
+    TxNotFound { tx_id: TxId },
+    /// The receiver did not confirm the transaction yet
+    ConfirmationUnavailable { tx_id: TxId },
+    /// The transaction is not in the state the call requires
+    InvalidState {
+        tx_id: TxId,
+        expected: ConfirmationStatus,
+        actual: ConfirmationStatus,
+    },
+    /// The confirmation window of the transaction passed
+    TxExpired { tx_id: TxId },
+    /// Too many confirmations failed signature verification
+    TxLocked { tx_id: TxId },
+    /// The call targets another network than the one the transaction was submitted to
+    NetworkMismatch {
+        tx_id: TxId,
+        expected: BlockchainNetwork,
+        actual: BlockchainNetwork,
+    },
+    /// The call data belongs to another network family than the submitted network
+    CallNetworkMismatch {
+        family: NetworkFamily,
+        network: BlockchainNetwork,
+    },
+    /// No signature scheme is registered for the network
+    UnsupportedNetwork(BlockchainNetwork),
+    /// The confirmation signature does not sign the transaction payload for the address
+    SignatureVerification { tx_id: TxId, reason: String },
+    /// Only the sender of the transaction can perform the call
+    NotSender { tx_id: TxId },
+    /// Only the receiver of the transaction can perform the call
+    NotReceiver { tx_id: TxId },
+    /// The simulation delivery is unknown or already acknowledged
+    DeliveryNotFound { delivery_id: u64 },
+    /// The storage backend failed
+    Storage(String),
+    /// The response could not be encoded
+    Encoding(String),
+}
+
+impl VaneError {
+    /// Stable JSON-RPC error code, taken from the implementation defined server errors range
+    pub fn code(&self) -> i32 {
+        match self {
+            VaneError::TxNotFound { .. } => -32001,
+            VaneError::ConfirmationUnavailable { .. } => -32002,
+            VaneError::InvalidState { .. } => -32003,
+            VaneError::TxExpired { .. } => -32004,
+            VaneError::TxLocked { .. } => -32005,
+            VaneError::NetworkMismatch { .. } => -32006,
+            VaneError::CallNetworkMismatch { .. } => -32007,
+            VaneError::UnsupportedNetwork(_) => -32008,
+            VaneError::SignatureVerification { .. } => -32009,
+            VaneError::NotSender { .. } => -32010,
+            VaneError::DeliveryNotFound { .. } => -32011,
+            VaneError::NotReceiver { .. } => -32012,
+            VaneError::Storage(_) => -32090,
+            VaneError::Encoding(_) => -32091,
+        }
+    }
+}
+
+impl std::fmt::Display for VaneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VaneError::TxNotFound { .. } => write!(f, "Transaction Not Found"),
+            VaneError::ConfirmationUnavailable { .. } => {
+                write!(f, "Confirmation data unavailable")
+            }
+            VaneError::InvalidState {
+                expected, actual, ..
+            } => write!(
+                f,
+                "Transaction is {actual:?} while {expected:?} is required"
+            ),
+            VaneError::TxExpired { .. } => write!(f, "Transaction expired"),
+            VaneError::TxLocked { .. } => {
+                write!(f, "Transaction locked after too many failed confirmations")
+            }
+            VaneError::NetworkMismatch {
+                expected, actual, ..
+            } => write!(
+                f,
+                "Transaction network mismatch, expected {expected:?} found {actual:?}"
+            ),
+            VaneError::CallNetworkMismatch { family, network } => write!(
+                f,
+                "Call data for {family:?} network family can not be submitted to {network:?}"
+            ),
+            VaneError::UnsupportedNetwork(network) => {
+                write!(f, "Blockchain network not supported: {network:?}")
+            }
+            VaneError::SignatureVerification { reason, .. } => {
+                write!(f, "Signature verification failed: {reason}")
+            }
+            VaneError::NotSender { .. } => {
+                write!(f, "Only the sender can perform the call on the transaction")
+            }
+            VaneError::NotReceiver { .. } => {
+                write!(f, "Only the receiver can perform the call on the transaction")
+            }
+            VaneError::DeliveryNotFound { .. } => {
+                write!(f, "Delivery not found or already acknowledged")
+            }
+            VaneError::Storage(reason) => write!(f, "Storage error: {reason}"),
+            VaneError::Encoding(reason) => write!(f, "Failed to encode response: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for VaneError {}
+
+impl From<VaneError> for ErrorObjectOwned {
+    fn from(err: VaneError) -> Self {
+        ErrorObject::owned(err.code(), err.to_string(), Some(err))
+    }
+}
+
+impl From<VaneError> for jsonrpsee::core::Error {
+    fn from(err: VaneError) -> Self {
+        jsonrpsee::core::Error::Call(CallError::Custom(err.into()))
+    }
+}
@@ -1,34 +1,77 @@
 pub use common::*;
-use frame_support::StorageHasher;
-use frame_support::Twox64Concat;
+pub use error::VaneError;
+pub mod address;
+pub mod error;
 use parity_scale_codec::{Decode, Encode};
 use serde::{Deserialize, Serialize};
-use derivative::Derivative;
-use tinyrand::{Rand, StdRand, RandRange};
 pub mod common {
 
-    use std::borrow::Cow;
-
-    use sp_core::{blake2_128, blake2_256};
-    use subxt::{tx::{DynamicPayload, Payload}, utils::{AccountId32,MultiAddress,MultiSignature}};
+    use sp_core::blake2_256;
+    use subxt::utils::{AccountId32,MultiAddress};
 
     use super::*;
     /// The transaction object which all operations will be applied upon
     /// `call`: encoded transaction function call
     /// `network`: network to which the transaction will be submitted to
     /// `lifetime`: maximum period of time in minutes should this transaction be valid on confirmation phase
+    /// `submitted_time`: unix time in millis at which the transaction was submitted
+    /// `salt`: random bytes mixed in the `tx_id` derivation, kept to re-derive the `tx_id` on audit
     /// `multi_id`: The computed address from receiver and sender, this should be kept hidden as it will be used for confirmation
-    #[derive(Debug, Encode, Serialize, Deserialize, Decode, Clone)]
+    /// `warnings`: risks found on the addresses at submission for both parties to review before confirming
+    #[derive(Debug, Encode, Serialize, Deserialize, Decode, Clone, PartialEq, Eq)]
     pub struct TxObject {
-        tx_id: String,
+        tx_id: TxId,
         pub call: VaneCallData,
         pub sender_address: VaneMultiAddress<AccountId32, ()>,
         pub receiver_address: VaneMultiAddress<AccountId32, ()>,
         multi_id: VaneMultiAddress<AccountId32, ()>,
         pub network: BlockchainNetwork,
         pub lifetime: Option<u8>,
-        //submitted_time:
+        pub submitted_time: u64,
+        salt: [u8; 16],
         pub lifetime_status: LifetimeStatus,
+        pub warnings: Vec<AddressWarning>,
+    }
+
+    /// Transaction identifier, hex encoded `blake2_256` hash of the transaction content
+    /// acting as a link among 3 objects (TxObject, TxSimulation, TxConfirmation)
+    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[serde(transparent)]
+    pub struct TxId(String);
+
+    impl TxId {
+        /// Derive the id from everything identifying the transaction
+        /// `submitted_time` and the random `salt` keep identical transfers between the same pair apart
+        pub fn derive(
+            sender_address: &VaneMultiAddress<AccountId32, ()>,
+            receiver_address: &VaneMultiAddress<AccountId32, ()>,
+            network: &BlockchainNetwork,
+            call: &VaneCallData,
+            submitted_time: u64,
+            salt: &[u8; 16],
+        ) -> Self {
+            let hash = (b"VANE_TX", sender_address, receiver_address, network, call, submitted_time, salt)
+                .using_encoded(blake2_256);
+            TxId(sp_core::bytes::to_hex(&hash, false))
+        }
+
+        pub fn as_str(&self) -> &str {
+            &self.0
+        }
+    }
+
+    impl std::fmt::Display for TxId {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+
+    /// Confirmation window in minutes used when the transaction `lifetime` is not set
+    pub const DEFAULT_TX_LIFETIME: u8 = 60;
+
+    /// Unix time in millis after which a transaction can no longer be confirmed
+    pub fn expiry_time(submitted_time: u64, lifetime: Option<u8>) -> u64 {
+        submitted_time + lifetime.unwrap_or(DEFAULT_TX_LIFETIME) as u64 * 60_000
     }
 
     impl TxObject {
@@ -38,8 +81,17 @@ pub mod common {
             receiver_address: MultiAddress<AccountId32, ()>,
             network: BlockchainNetwork,
         ) -> Self {
-            let tx_id = call.get_tx_id();
-            let tx_id = String::from_utf8(tx_id).expect("Failed to convert tx id from bytes");
+            let submitted_time = chrono::Utc::now().timestamp_millis() as u64;
+            // CSPRNG salt so ids can not be predicted ahead of submission
+            let salt: [u8; 16] = rand::random();
+            let tx_id = TxId::derive(
+                &sender_address.clone().into(),
+                &receiver_address.clone().into(),
+                &network,
+                &call,
+                submitted_time,
+                &salt,
+            );
 
             let multi_id = (sender_address.clone(), receiver_address.clone(),b"VANE").using_encoded(blake2_256);
             let multi_id: MultiAddress<AccountId32, ()> = MultiAddress::Address32(multi_id);
@@ -47,16 +99,43 @@ pub mod common {
                 tx_id,
                 call,
                 sender_address: sender_address.clone().into(),
-                receiver_address: sender_address.into(),
+                receiver_address: receiver_address.into(),
                 multi_id: multi_id.into(),
                 network,
                 lifetime: None,
+                submitted_time,
+                salt,
                 lifetime_status: LifetimeStatus::Valid,
+                warnings: Vec::new(),
             }
         }
 
+        pub fn expires_at(&self) -> u64 {
+            expiry_time(self.submitted_time, self.lifetime)
+        }
+
+        pub fn is_expired(&self, now: u64) -> bool {
+            self.lifetime_status == LifetimeStatus::Invalid || self.expires_at() <= now
+        }
+
         pub fn get_multi_id(&self) -> VaneMultiAddress<AccountId32, ()> {
-            self.multi_id.clone().into()
+            self.multi_id.clone()
+        }
+
+        pub fn get_tx_id(&self) -> TxId {
+            self.tx_id.clone()
+        }
+
+        /// Whether the `tx_id` matches the transaction content
+        pub fn verify_tx_id(&self) -> bool {
+            TxId::derive(
+                &self.sender_address,
+                &self.receiver_address,
+                &self.network,
+                &self.call,
+                self.submitted_time,
+                &self.salt,
+            ) == self.tx_id
         }
     }
 
@@ -80,25 +159,146 @@ pub mod common {
     impl VaneCallData {
 
         pub fn new(network: BlockchainNetwork, amount: u128) -> Self {
-            match network {
-                BlockchainNetwork::Polkadot => {
+            match network.family() {
+                NetworkFamily::Substrate => {
                     VaneCallData::SubstrateCallData { amount }
                 },
-                _ => todo!()
+                NetworkFamily::Ethereum => {
+                    VaneCallData::EthereumCallData { amount }
+                },
+                NetworkFamily::Solana => {
+                    VaneCallData::SolanaCallData { amount, extra_receivers: Vec::new() }
+                }
             }
         }
 
-        pub fn get_tx_id(&self) -> Vec<u8> {
-            match self{
-                VaneCallData::SubstrateCallData{amount} => {
-                    let mut rand = StdRand::default();
-                    Twox64Concat::hash(&format!("{}{}",amount, rand.next_u128()).encode()[..])
-               },
-               _ => todo!()
+        /// Network family the call data can be submitted to
+        pub fn family(&self) -> NetworkFamily {
+            match self {
+                VaneCallData::SubstrateCallData { .. } => NetworkFamily::Substrate,
+                VaneCallData::SolanaCallData { .. } => NetworkFamily::Solana,
+                VaneCallData::EthereumCallData { .. } => NetworkFamily::Ethereum,
+            }
+        }
+
+        pub fn matches_network(&self, network: &BlockchainNetwork) -> bool {
+            self.family() == network.family()
+        }
+
+        pub fn amount(&self) -> u128 {
+            match self {
+                VaneCallData::SubstrateCallData { amount }
+                | VaneCallData::SolanaCallData { amount, .. }
+                | VaneCallData::EthereumCallData { amount } => *amount,
             }
         }
     }
 
+    /// Tag prefixing every confirmation payload so the signatures can not be reused by other protocols
+    pub const CONFIRMATION_DOMAIN: &[u8] = b"VANE_CONFIRMATION_V1";
+    /// Tag prefixing the payload the sender signs to revert a transaction
+    /// so a confirmation signature can never be replayed as a revert and the other way around
+    pub const REVERT_DOMAIN: &[u8] = b"VANE_REVERT_V1";
+
+    /// Party confirming a transaction
+    #[derive(Debug, Encode, Decode, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+    pub enum ConfirmationRole {
+        Sender,
+        Receiver,
+    }
+
+    /// The message both parties sign to confirm a transaction
+    /// Binding the transaction identifiers, the parties, the network, the amount, the signing role and the expiry
+    /// a signature is only valid for a single confirmation of a single transaction
+    /// Wallets can sign either the SCALE encoding or the human readable rendering ( `Display` )
+    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct ConfirmationPayload {
+        pub domain: Vec<u8>,
+        pub tx_id: TxId,
+        pub multi_id: VaneMultiAddress<AccountId32, ()>,
+        pub sender_address: VaneMultiAddress<AccountId32, ()>,
+        pub receiver_address: VaneMultiAddress<AccountId32, ()>,
+        pub network: BlockchainNetwork,
+        pub amount: u128,
+        pub role: ConfirmationRole,
+        // Unix time in millis after which the confirmation is no longer accepted
+        pub expires_at: u64,
+    }
+
+    impl ConfirmationPayload {
+        pub fn new(tx: &TxObject, role: ConfirmationRole) -> Self {
+            Self {
+                domain: CONFIRMATION_DOMAIN.to_vec(),
+                tx_id: tx.get_tx_id(),
+                multi_id: tx.get_multi_id(),
+                sender_address: tx.sender_address.clone(),
+                receiver_address: tx.receiver_address.clone(),
+                network: tx.network.clone(),
+                amount: tx.call.amount(),
+                role,
+                expires_at: tx.expires_at(),
+            }
+        }
+
+        /// The message the sender signs to withdraw the transaction
+        pub fn revert(tx: &TxObject) -> Self {
+            Self {
+                domain: REVERT_DOMAIN.to_vec(),
+                ..Self::new(tx, ConfirmationRole::Sender)
+            }
+        }
+
+        /// Every rendering of the payload a confirmation signature is accepted for
+        pub fn renderings(&self) -> [Vec<u8>; 2] {
+            [self.encode(), self.to_string().into_bytes()]
+        }
+
+        /// Multi id, sender and receiver as shown to the signer
+        pub fn rendered_addresses(&self) -> [String; 3] {
+            [
+                hex_address(&self.multi_id),
+                render_address(&self.sender_address, &self.network),
+                render_address(&self.receiver_address, &self.network),
+            ]
+        }
+    }
+
+    impl std::fmt::Display for ConfirmationPayload {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            writeln!(f, "{}", String::from_utf8_lossy(&self.domain))?;
+            writeln!(f, "Role: {:?}", self.role)?;
+            let [multi_id, sender, receiver] = self.rendered_addresses();
+            writeln!(f, "Transaction: {}", self.tx_id)?;
+            writeln!(f, "Multi id: {multi_id}")?;
+            writeln!(f, "Sender: {sender}")?;
+            writeln!(f, "Receiver: {receiver}")?;
+            writeln!(f, "Network: {:?}", self.network)?;
+            writeln!(f, "Amount: {}", self.amount)?;
+            write!(f, "Expires at: {}", self.expires_at)
+        }
+    }
+
+    /// Rendering of the address in the `network` format falling back to the address bytes
+    fn render_address(address: &VaneMultiAddress<AccountId32, ()>, network: &BlockchainNetwork) -> String {
+        address
+            .to_network_string(network)
+            .unwrap_or_else(|_| hex_address(address))
+    }
+
+    /// Hex rendering of the address bytes, `Raw` addresses are shown as is when they are text
+    fn hex_address(address: &VaneMultiAddress<AccountId32, ()>) -> String {
+        match address {
+            VaneMultiAddress::Id(account) => sp_core::bytes::to_hex(&account.0, false),
+            VaneMultiAddress::Address32(bytes) => sp_core::bytes::to_hex(bytes, false),
+            VaneMultiAddress::Address20(bytes) => sp_core::bytes::to_hex(bytes, false),
+            VaneMultiAddress::Raw(raw) => match std::str::from_utf8(raw) {
+                Ok(text) => text.to_string(),
+                Err(_) => sp_core::bytes::to_hex(raw, false),
+            },
+            VaneMultiAddress::Index(_) => "index".to_string(),
+        }
+    }
+
     // #[derive(Derivative, Serialize, Deserialize, Encode,Decode)]
     // #[derivative(
     //     Clone(bound = "CallData: Clone"),
@@ -128,6 +328,42 @@ pub mod common {
         Ready,
         Accepted,
         Rejected,
+        Reverted,
+        Expired,
+        Locked,
+    }
+
+    /// Progress of a confirmed transaction towards the network simulation and router layer
+    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    pub enum SimulationStatus {
+        /// Waiting for both parties to confirm
+        NotReady,
+        /// Confirmed and waiting in the simulation queue
+        Queued,
+        /// Taken from the queue by the network simulation and router layer
+        Propagated,
+    }
+
+    /// Full lifecycle view of a transaction returned when polling its state
+    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize)]
+    pub struct TxStateObject {
+        pub tx: TxObject,
+        pub confirmation_status: ConfirmationStatus,
+        pub receiver_signed: bool,
+        pub sender_signed: bool,
+        pub simulation_status: SimulationStatus,
+        /// Latest status reported by the router once the transaction was submitted
+        pub execution_status: Option<TxExecutionStatus>,
+    }
+
+    /// Encoding of the response requested by the client
+    /// `Scale` returns hex encoded SCALE bytes while `Json` returns the objects as is
+    #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ResponseEncoding {
+        #[default]
+        Scale,
+        Json,
     }
 
     impl From<TxObject> for TxConfirmationObject {
@@ -139,19 +375,109 @@ pub mod common {
                 sender_sig: None,
                 confirmation_status: ConfirmationStatus::WaitingForReceiver,
                 network: value.network,
-                sender_address: value.sender_address.into(),
-                receiver_address: value.receiver_address.into(),
+                sender_address: value.sender_address,
+                receiver_address: value.receiver_address,
+                lifetime: value.lifetime,
+                submitted_time: value.submitted_time,
+                warnings: value.warnings,
+                trusted_receiver: false,
             }
         }
     }
 
+    /// Returned to the sender once the transaction is submitted for confirmation
+    /// `tx_id` and `multi_id` together identify the transaction in the confirmation phase
+    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct TxSubmissionObject {
+        pub tx_id: TxId,
+        pub multi_id: VaneMultiAddress<AccountId32, ()>,
+        pub warnings: Vec<AddressWarning>,
+    }
+
+    /// Risk of losing funds found on an address of a transaction
+    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    pub enum AddressRisk {
+        /// SS58 address encoded with the prefix of another network
+        ForeignSs58Prefix { prefix: u16, network: Option<BlockchainNetwork> },
+        /// 20 bytes address on a network without H160 accounts
+        EvmAddressOnNonEvmNetwork,
+        /// 32 bytes address on a network with H160 accounts
+        NonEvmAddressOnEvmNetwork,
+        /// Address which is not valid on the network for any other reason
+        InvalidForNetwork(String),
+        /// Listed smart contract, which may not be able to handle the transfer
+        KnownContract(String),
+        /// Listed exchange deposit address, which may require a memo or not credit this network
+        ExchangeDeposit(String),
+    }
+
+    /// `AddressRisk` of the address of one party of a transaction
+    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct AddressWarning {
+        pub role: ConfirmationRole,
+        pub address: VaneMultiAddress<AccountId32, ()>,
+        pub risk: AddressRisk,
+    }
+
+    /// Receiver a sender already paid through a transaction confirmed by both parties on the network
+    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    pub struct KnownCounterparty {
+        pub receiver: VaneMultiAddress<AccountId32, ()>,
+        pub network: BlockchainNetwork,
+        /// Transactions to the receiver confirmed by both parties
+        pub confirmed_txs: u32,
+        /// Unix time in millis of the latest confirmed transaction
+        pub last_confirmed: u64,
+    }
+
+    /// Incremental update pushed to the subscribers of the addresses involved in a transaction
+    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    pub enum TxEvent {
+        /// A new transaction is waiting for the receiver confirmation
+        Submitted(TxObject),
+        /// The receiver confirmed, waiting for the sender confirmation
+        ReceiverConfirmed(TxConfirmationObject),
+        /// Both parties confirmed and the transaction is queued for simulation
+        SenderConfirmed(TxConfirmationObject),
+        /// The sender withdrew the transaction before it was propagated
+        Reverted {
+            tx_id: TxId,
+            multi_id: VaneMultiAddress<AccountId32, ()>,
+        },
+        /// The confirmation window passed before both parties confirmed
+        Expired {
+            tx_id: TxId,
+            multi_id: VaneMultiAddress<AccountId32, ()>,
+        },
+        /// Too many confirmations failed signature verification, no further confirmation is accepted
+        Locked {
+            tx_id: TxId,
+            multi_id: VaneMultiAddress<AccountId32, ()>,
+        },
+        /// The network router layer reported progress of the transaction on its network
+        Executed {
+            tx_id: TxId,
+            multi_id: VaneMultiAddress<AccountId32, ()>,
+            status: TxExecutionStatus,
+        },
+    }
+
+    /// Item of the confirmation subscriptions
+    /// `Snapshot` of the pending transactions of the address once on subscription
+    /// followed by an `Event` for every change on them as long as the subscription lives
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    pub enum SubscriptionUpdate<T> {
+        Snapshot(Vec<T>),
+        Event(Box<TxEvent>),
+    }
+
     /// Object to be propagated to network simulator and router layer
     #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize)]
     pub struct TxSimulationObject {
         sender_address: VaneMultiAddress<AccountId32, ()>,
         receiver_address: VaneMultiAddress<AccountId32, ()>,
         // Tx hash representation
-        tx_id: String,
+        tx_id: TxId,
         // Tx function encoded
         call: VaneCallData,
         // State of the Tx to be confirmed
@@ -160,13 +486,55 @@ pub mod common {
         network: BlockchainNetwork,
     }
 
+    /// `TxSimulationObject` leased to a consumer of the confirmed transactions feed
+    /// `delivery_id` must be acknowledged once handled otherwise the object is delivered again
+    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize)]
+    pub struct TxSimulationDelivery {
+        pub delivery_id: u64,
+        pub tx: TxSimulationObject,
+    }
+
+    /// Progress of a confirmed transaction on its network reported by the network router layer
+    /// `tx_hash` is the hash of the transaction on the network
+    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    pub enum TxExecutionStatus {
+        /// Accepted by the network, waiting to be included in a block
+        Submitted { tx_hash: String },
+        /// Included in the best block `block_hash` at `extrinsic_index`
+        InBlock { tx_hash: String, block_hash: String, extrinsic_index: u32 },
+        /// The best block including the transaction was retracted, waiting to be included again
+        Retracted { tx_hash: String },
+        /// Included in the finalized block `block_hash` at `extrinsic_index`
+        Finalized { tx_hash: String, block_hash: String, extrinsic_index: u32 },
+        /// Dropped from the transaction pool
+        Dropped { tx_hash: String, reason: String },
+        /// Rejected by the network as invalid
+        Invalid { tx_hash: String, reason: String },
+        /// Replaced by another transaction of the relayer with the same nonce
+        Usurped { tx_hash: String },
+        /// The transaction could not be submitted or failed on execution
+        Failed { reason: String },
+    }
+
+    impl TxExecutionStatus {
+        /// Whether no further status follows
+        pub fn is_final(&self) -> bool {
+            !matches!(
+                self,
+                TxExecutionStatus::Submitted { .. }
+                    | TxExecutionStatus::InBlock { .. }
+                    | TxExecutionStatus::Retracted { .. }
+            )
+        }
+    }
+
     /// Struct to be sent in the network for confirmation from sender and receiver
     #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize, PartialEq, Eq)]
     pub struct TxConfirmationObject {
         sender_address: VaneMultiAddress<AccountId32, ()>,
         receiver_address: VaneMultiAddress<AccountId32, ()>,
         // Tx hash representation and acting as a link among 3 objects (TxObject, TxSimulation, TxConfirmation)
-        tx_id: String,
+        tx_id: TxId,
         pub call: VaneCallData,
         // State of the Tx to be confirmed
         confirmation_status: ConfirmationStatus,
@@ -176,6 +544,14 @@ pub mod common {
         sender_sig: Option<Vec<u8>>,
         // Blockchain network to submit the Tx to
         network: BlockchainNetwork,
+        // Confirmation window in minutes
+        lifetime: Option<u8>,
+        // Unix time in millis at which the Tx was submitted
+        submitted_time: u64,
+        // Risks found on the addresses at submission
+        warnings: Vec<AddressWarning>,
+        // Whether the sender already completed a transaction to the receiver on the network
+        trusted_receiver: bool,
     }
 
     impl From<TxConfirmationObject> for TxSimulationObject {
@@ -191,6 +567,32 @@ pub mod common {
         }
     }
 
+    impl TxSimulationObject {
+        pub fn get_tx_id(&self) -> TxId {
+            self.tx_id.clone()
+        }
+
+        pub fn get_sender_address(&self) -> VaneMultiAddress<AccountId32, ()> {
+            self.sender_address.clone()
+        }
+
+        pub fn get_receiver_address(&self) -> VaneMultiAddress<AccountId32, ()> {
+            self.receiver_address.clone()
+        }
+
+        pub fn get_call(&self) -> VaneCallData {
+            self.call.clone()
+        }
+
+        pub fn get_network(&self) -> BlockchainNetwork {
+            self.network.clone()
+        }
+
+        pub fn get_confirmation_status(&self) -> ConfirmationStatus {
+            self.confirmation_status.clone()
+        }
+    }
+
     impl TxConfirmationObject {
         pub fn update_confirmation_status(&mut self, status: ConfirmationStatus) {
             self.confirmation_status = status
@@ -207,16 +609,60 @@ pub mod common {
         pub fn get_confirmation_status(&self) -> ConfirmationStatus {
             self.confirmation_status.clone()
         }
+
+        pub fn expires_at(&self) -> u64 {
+            expiry_time(self.submitted_time, self.lifetime)
+        }
+
+        pub fn is_expired(&self, now: u64) -> bool {
+            self.confirmation_status == ConfirmationStatus::Expired || self.expires_at() <= now
+        }
+
+        pub fn get_sender_address(&self) -> VaneMultiAddress<AccountId32, ()> {
+            self.sender_address.clone()
+        }
+
+        pub fn get_receiver_address(&self) -> VaneMultiAddress<AccountId32, ()> {
+            self.receiver_address.clone()
+        }
+
+        pub fn get_receiver_sig(&self) -> Option<Vec<u8>> {
+            self.receiver_sig.clone()
+        }
+
+        pub fn get_sender_sig(&self) -> Option<Vec<u8>> {
+            self.sender_sig.clone()
+        }
+
+        pub fn get_tx_id(&self) -> TxId {
+            self.tx_id.clone()
+        }
+
+        pub fn get_network(&self) -> BlockchainNetwork {
+            self.network.clone()
+        }
+
+        pub fn get_warnings(&self) -> Vec<AddressWarning> {
+            self.warnings.clone()
+        }
+
+        pub fn set_trusted_receiver(&mut self, trusted: bool) {
+            self.trusted_receiver = trusted
+        }
+
+        pub fn is_trusted_receiver(&self) -> bool {
+            self.trusted_receiver
+        }
     }
 
-    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize)]
+    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize, PartialEq, Eq)]
     pub enum LifetimeStatus {
         Valid,
         Invalid,
     }
 
     /// Supported networks
-    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize, PartialEq, Eq)]
+    #[derive(Debug, Encode, Decode, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
     pub enum BlockchainNetwork {
         Polkadot,
         Kusama,
@@ -228,6 +674,29 @@ pub mod common {
         Solana,
     }
 
+    /// Networks sharing the same transaction format
+    #[derive(Debug, Encode, Decode, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+    pub enum NetworkFamily {
+        Substrate,
+        Ethereum,
+        Solana,
+    }
+
+    impl BlockchainNetwork {
+        pub fn family(&self) -> NetworkFamily {
+            match self {
+                BlockchainNetwork::Polkadot
+                | BlockchainNetwork::Kusama
+                | BlockchainNetwork::Astar
+                | BlockchainNetwork::Moonbeam => NetworkFamily::Substrate,
+                BlockchainNetwork::Ethereum
+                | BlockchainNetwork::Optimism
+                | BlockchainNetwork::Arbitrum => NetworkFamily::Ethereum,
+                BlockchainNetwork::Solana => NetworkFamily::Solana,
+            }
+        }
+    }
+
     /// Account data types
     /// This is just similar to what `MultiAddress` is but with `serde:Serialize` implemented
     #[derive(
@@ -243,7 +712,6 @@ pub mod common {
         Hash,
         PartialOrd, Ord
     )]
-    #[cfg_attr(feature = "std", derive(Hash))]
     pub enum VaneMultiAddress<AccountId, AccountIndex> {
         /// It's an account ID (pubkey).
         Id(AccountId),
@@ -280,4 +748,87 @@ pub mod common {
             }
         }
     }
+
+    /// Reasons an address can not be parsed, formatted or turned into the key material of a signature scheme
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum AddressError {
+        /// The address variant carries no key material ( i.e `Index` )
+        UnsupportedVariant(&'static str),
+        /// The key material has the wrong number of bytes
+        InvalidLength { expected: usize, found: usize },
+        /// The bytes are not a point of the scheme curve
+        InvalidPublicKey(&'static str),
+        /// The text is not an address in the encoding of the network
+        InvalidEncoding(String),
+        /// The EIP-55 mixed case checksum does not match
+        InvalidChecksum,
+        /// The address is valid but belongs to another network
+        NetworkMismatch(String),
+    }
+
+    impl std::fmt::Display for AddressError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                AddressError::UnsupportedVariant(variant) => {
+                    write!(f, "{variant} address carries no public key")
+                }
+                AddressError::InvalidLength { expected, found } => {
+                    write!(f, "expected {expected} bytes address, found {found} bytes")
+                }
+                AddressError::InvalidPublicKey(scheme) => write!(f, "not a valid {scheme} public key"),
+                AddressError::InvalidEncoding(reason) => write!(f, "invalid address encoding: {reason}"),
+                AddressError::InvalidChecksum => write!(f, "invalid EIP-55 address checksum"),
+                AddressError::NetworkMismatch(reason) => write!(f, "address network mismatch: {reason}"),
+            }
+        }
+    }
+
+    impl std::error::Error for AddressError {}
+
+    impl VaneMultiAddress<AccountId32, ()> {
+        /// Bytes the address carries, `Raw` bytes are taken as is
+        fn key_bytes(&self) -> Result<&[u8], AddressError> {
+            match self {
+                VaneMultiAddress::Id(account) => Ok(&account.0[..]),
+                VaneMultiAddress::Address32(bytes) => Ok(&bytes[..]),
+                VaneMultiAddress::Address20(bytes) => Ok(&bytes[..]),
+                VaneMultiAddress::Raw(raw) => Ok(&raw[..]),
+                VaneMultiAddress::Index(_) => Err(AddressError::UnsupportedVariant("Index")),
+            }
+        }
+
+        /// 32 bytes Substrate account id, no curve check as it may be the hash of an ecdsa key
+        pub fn account_id_32(&self) -> Result<[u8; 32], AddressError> {
+            let bytes = self.key_bytes()?;
+            bytes.try_into().map_err(|_| AddressError::InvalidLength {
+                expected: 32,
+                found: bytes.len(),
+            })
+        }
+
+        /// 20 bytes EVM address, the public key is only known once recovered from a signature
+        pub fn evm_address(&self) -> Result<[u8; 20], AddressError> {
+            let bytes = self.key_bytes()?;
+            bytes.try_into().map_err(|_| AddressError::InvalidLength {
+                expected: 20,
+                found: bytes.len(),
+            })
+        }
+
+        /// sr25519 public key, checked to be a valid ristretto point
+        pub fn sr25519_public(&self) -> Result<[u8; 32], AddressError> {
+            let public = self.account_id_32()?;
+            schnorrkel::PublicKey::from_bytes(&public)
+                .map_err(|_| AddressError::InvalidPublicKey("sr25519"))?;
+            Ok(public)
+        }
+
+        /// ed25519 public key, checked to be a valid edwards point
+        pub fn ed25519_public(&self) -> Result<[u8; 32], AddressError> {
+            let public = self.account_id_32()?;
+            ed25519_zebra::VerificationKey::try_from(public)
+                .map_err(|_| AddressError::InvalidPublicKey("ed25519"))?;
+            Ok(public)
+        }
+    }
 }
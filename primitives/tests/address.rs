@@ -0,0 +1,151 @@
+//! Network specific address parsing and formatting
+
+use primitives::{AddressError, BlockchainNetwork, VaneMultiAddress};
+use subxt::utils::AccountId32;
+
+type Address = VaneMultiAddress<AccountId32, ()>;
+
+const ALICE: [u8; 32] = [
+    0xd4, 0x35, 0x93, 0xc7, 0x15, 0xfd, 0xd3, 0x1c, 0x61, 0x14, 0x1a, 0xbd, 0x04, 0xa9, 0x9f, 0xd6,
+    0x82, 0x2c, 0x85, 0x58, 0x85, 0x4c, 0xcd, 0xe3, 0x9a, 0x56, 0x84, 0xe7, 0xa5, 0x6d, 0xa2, 0x7d,
+];
+const ALICE_POLKADOT: &str = "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5";
+
+#[test]
+fn ss58_round_trip_on_polkadot() {
+    let alice = Address::parse(ALICE_POLKADOT, &BlockchainNetwork::Polkadot).unwrap();
+
+    assert_eq!(alice, Address::Address32(ALICE));
+    assert_eq!(
+        alice
+            .to_network_string(&BlockchainNetwork::Polkadot)
+            .unwrap(),
+        ALICE_POLKADOT
+    );
+}
+
+#[test]
+fn ss58_of_another_network_is_rejected() {
+    let kusama = Address::Address32(ALICE)
+        .to_network_string(&BlockchainNetwork::Kusama)
+        .unwrap();
+
+    assert!(matches!(
+        Address::parse(&kusama, &BlockchainNetwork::Polkadot),
+        Err(AddressError::NetworkMismatch(_))
+    ));
+    assert!(matches!(
+        Address::parse(ALICE_POLKADOT, &BlockchainNetwork::Solana),
+        Err(AddressError::NetworkMismatch(_))
+    ));
+}
+
+#[test]
+fn eip55_checksum_is_enforced() {
+    let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+    let address = Address::parse(checksummed, &BlockchainNetwork::Ethereum).unwrap();
+
+    assert_eq!(
+        address
+            .to_network_string(&BlockchainNetwork::Arbitrum)
+            .unwrap(),
+        checksummed
+    );
+    // single case addresses carry no checksum
+    assert_eq!(
+        Address::parse(&checksummed.to_lowercase(), &BlockchainNetwork::Optimism).unwrap(),
+        address
+    );
+    assert_eq!(
+        Address::parse(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD",
+            &BlockchainNetwork::Ethereum
+        ),
+        Err(AddressError::InvalidChecksum)
+    );
+}
+
+#[test]
+fn evm_address_is_rejected_on_substrate_and_solana() {
+    let address = Address::Address20([7; 20]);
+
+    assert!(address.check_network(&BlockchainNetwork::Polkadot).is_err());
+    assert!(address.check_network(&BlockchainNetwork::Solana).is_err());
+    assert!(address.check_network(&BlockchainNetwork::Moonbeam).is_ok());
+    assert!(matches!(
+        Address::parse(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            &BlockchainNetwork::Kusama
+        ),
+        Err(AddressError::NetworkMismatch(_))
+    ));
+}
+
+#[test]
+fn base58_round_trip_on_solana() {
+    let system_program = "11111111111111111111111111111111";
+    let address = Address::parse(system_program, &BlockchainNetwork::Solana).unwrap();
+
+    assert_eq!(address, Address::Address32([0; 32]));
+    assert_eq!(
+        address
+            .to_network_string(&BlockchainNetwork::Solana)
+            .unwrap(),
+        system_program
+    );
+    // `Raw` addresses may carry the base58 text
+    let vote_program = "Vote111111111111111111111111111111111111111";
+    assert_eq!(
+        Address::Raw(vote_program.as_bytes().to_vec())
+            .to_network_string(&BlockchainNetwork::Solana)
+            .unwrap(),
+        vote_program
+    );
+}
+
+#[test]
+fn substrate_account_is_rejected_on_moonbeam() {
+    let moonbeam = BlockchainNetwork::Moonbeam;
+    // Moonbeam has an SS58 prefix yet its accounts are 20 bytes
+    let ss58 = Address::Address32(ALICE)
+        .to_network_string(&BlockchainNetwork::Polkadot)
+        .unwrap();
+
+    assert!(matches!(
+        Address::parse(&ss58, &moonbeam),
+        Err(AddressError::NetworkMismatch(_))
+    ));
+    assert!(matches!(
+        Address::Address32(ALICE).to_network_string(&moonbeam),
+        Err(AddressError::NetworkMismatch(_))
+    ));
+    assert!(matches!(
+        Address::Id(AccountId32(ALICE)).check_network(&moonbeam),
+        Err(AddressError::NetworkMismatch(_))
+    ));
+    assert!(matches!(
+        Address::Raw(ALICE.to_vec()).check_network(&moonbeam),
+        Err(AddressError::NetworkMismatch(_))
+    ));
+}
+
+#[test]
+fn evm_address_round_trip_on_moonbeam() {
+    let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+    let address = Address::parse(checksummed, &BlockchainNetwork::Moonbeam).unwrap();
+
+    assert!(matches!(address, Address::Address20(_)));
+    assert_eq!(
+        address
+            .to_network_string(&BlockchainNetwork::Moonbeam)
+            .unwrap(),
+        checksummed
+    );
+    // 20 raw bytes are an EVM address as well
+    assert_eq!(
+        Address::Raw(address.evm_address().unwrap().to_vec())
+            .to_network_string(&BlockchainNetwork::Moonbeam)
+            .unwrap(),
+        checksummed
+    );
+}
@@ -0,0 +1,167 @@
+//! Invariants of the transaction objects along the confirmation flow
+//! `TxObject` -> `TxConfirmationObject` -> `TxSimulationObject`
+
+use parity_scale_codec::{Decode, Encode};
+use primitives::{
+    BlockchainNetwork, ConfirmationPayload, ConfirmationRole, ConfirmationStatus,
+    SubscriptionUpdate, TxConfirmationObject, TxEvent, TxObject, TxSimulationObject,
+    VaneCallData, VaneMultiAddress,
+};
+use proptest::prelude::*;
+use subxt::utils::{AccountId32, MultiAddress};
+
+fn address() -> impl Strategy<Value = MultiAddress<AccountId32, ()>> {
+    prop_oneof![
+        any::<[u8; 32]>().prop_map(|bytes| MultiAddress::Id(AccountId32(bytes))),
+        any::<[u8; 32]>().prop_map(MultiAddress::Address32),
+        any::<[u8; 20]>().prop_map(MultiAddress::Address20),
+        proptest::collection::vec(any::<u8>(), 0..64).prop_map(MultiAddress::Raw),
+    ]
+}
+
+fn network() -> impl Strategy<Value = BlockchainNetwork> {
+    prop_oneof![
+        Just(BlockchainNetwork::Polkadot),
+        Just(BlockchainNetwork::Kusama),
+        Just(BlockchainNetwork::Astar),
+        Just(BlockchainNetwork::Moonbeam),
+        Just(BlockchainNetwork::Ethereum),
+        Just(BlockchainNetwork::Optimism),
+        Just(BlockchainNetwork::Arbitrum),
+        Just(BlockchainNetwork::Solana),
+    ]
+}
+
+prop_compose! {
+    fn tx_object()(
+        sender in address(),
+        receiver in address(),
+        network in network(),
+        amount in any::<u128>(),
+    ) -> TxObject {
+        TxObject::new(VaneCallData::new(network.clone(), amount), sender, receiver, network)
+    }
+}
+
+proptest! {
+    #[test]
+    fn tx_object_keeps_both_parties(
+        sender in address(),
+        receiver in address(),
+        network in network(),
+        amount in any::<u128>(),
+    ) {
+        let call = VaneCallData::new(network.clone(), amount);
+        let tx = TxObject::new(call.clone(), sender.clone(), receiver.clone(), network.clone());
+
+        prop_assert_eq!(tx.sender_address, VaneMultiAddress::from(sender));
+        prop_assert_eq!(tx.receiver_address, VaneMultiAddress::from(receiver));
+        prop_assert_eq!(tx.network, network);
+        prop_assert_eq!(tx.call, call);
+    }
+
+    #[test]
+    fn confirmation_object_preserves_tx(tx in tx_object()) {
+        let confirmation: TxConfirmationObject = tx.clone().into();
+
+        prop_assert_eq!(confirmation.get_sender_address(), tx.sender_address.clone());
+        prop_assert_eq!(confirmation.get_receiver_address(), tx.receiver_address.clone());
+        prop_assert_eq!(confirmation.get_network(), tx.network.clone());
+        prop_assert_eq!(&confirmation.call, &tx.call);
+        prop_assert_eq!(confirmation.get_tx_id(), tx.get_tx_id());
+        prop_assert_eq!(confirmation.expires_at(), tx.expires_at());
+        prop_assert_eq!(confirmation.get_confirmation_status(), ConfirmationStatus::WaitingForReceiver);
+        prop_assert!(confirmation.get_receiver_sig().is_none());
+        prop_assert!(confirmation.get_sender_sig().is_none());
+    }
+
+    #[test]
+    fn simulation_object_preserves_confirmation(
+        tx in tx_object(),
+        receiver_sig in proptest::collection::vec(any::<u8>(), 64),
+        sender_sig in proptest::collection::vec(any::<u8>(), 64),
+    ) {
+        let mut confirmation: TxConfirmationObject = tx.clone().into();
+        confirmation.set_receiver_sig(receiver_sig);
+        confirmation.set_sender_sig(sender_sig);
+        confirmation.update_confirmation_status(ConfirmationStatus::Ready);
+        let simulation: TxSimulationObject = confirmation.into();
+
+        prop_assert_eq!(simulation.get_tx_id(), tx.get_tx_id());
+        prop_assert_eq!(simulation.get_sender_address(), tx.sender_address);
+        prop_assert_eq!(simulation.get_receiver_address(), tx.receiver_address);
+        prop_assert_eq!(simulation.get_network(), tx.network);
+        prop_assert_eq!(simulation.get_call(), tx.call);
+        prop_assert_eq!(simulation.get_confirmation_status(), ConfirmationStatus::Ready);
+    }
+
+    #[test]
+    fn tx_object_scale_round_trip(tx in tx_object()) {
+        let decoded = TxObject::decode(&mut &tx.encode()[..]).unwrap();
+
+        prop_assert!(decoded.verify_tx_id());
+        prop_assert_eq!(decoded, tx);
+    }
+
+    #[test]
+    fn multi_id_is_deterministic_per_direction(
+        sender in address(),
+        receiver in address(),
+        network in network(),
+        amount in any::<u128>(),
+    ) {
+        let call = VaneCallData::new(network.clone(), amount);
+        let first = TxObject::new(call.clone(), sender.clone(), receiver.clone(), network.clone());
+        let second = TxObject::new(call.clone(), sender.clone(), receiver.clone(), network.clone());
+        let reversed = TxObject::new(call, receiver.clone(), sender.clone(), network);
+
+        // the same pair shares the multi_id while each transfer keeps its own tx_id
+        prop_assert_eq!(first.get_multi_id(), second.get_multi_id());
+        prop_assert_ne!(first.get_tx_id(), second.get_tx_id());
+        // swapping the parties never resolves to the same transfers
+        if sender != receiver {
+            prop_assert_ne!(first.get_multi_id(), reversed.get_multi_id());
+        }
+    }
+
+    #[test]
+    fn confirmation_payload_is_bound_to_role(tx in tx_object()) {
+        let receiver = ConfirmationPayload::new(&tx, ConfirmationRole::Receiver);
+        let sender = ConfirmationPayload::new(&tx, ConfirmationRole::Sender);
+
+        prop_assert_eq!(&receiver.tx_id, &tx.get_tx_id());
+        prop_assert_eq!(&receiver.multi_id, &tx.get_multi_id());
+        prop_assert_eq!(&receiver.receiver_address, &tx.receiver_address);
+        prop_assert_eq!(receiver.amount, tx.call.amount());
+        prop_assert_ne!(receiver.encode(), sender.encode());
+        prop_assert_ne!(receiver.to_string(), sender.to_string());
+    }
+
+    #[test]
+    fn subscription_update_json_round_trip(tx in tx_object()) {
+        let snapshot = SubscriptionUpdate::Snapshot(vec![tx.clone()]);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        prop_assert_eq!(serde_json::from_str::<SubscriptionUpdate<TxObject>>(&json).unwrap(), snapshot);
+
+        // events read the same whatever the snapshot item of the subscription
+        let event = SubscriptionUpdate::<TxObject>::Event(Box::new(TxEvent::Submitted(tx)));
+        let json = serde_json::to_string(&event).unwrap();
+        prop_assert_eq!(serde_json::from_str::<SubscriptionUpdate<TxObject>>(&json).unwrap(), event.clone());
+        let SubscriptionUpdate::Event(tx_event) = event else { unreachable!() };
+        prop_assert_eq!(
+            serde_json::from_str::<SubscriptionUpdate<TxConfirmationObject>>(&json).unwrap(),
+            SubscriptionUpdate::Event(tx_event)
+        );
+    }
+
+    #[test]
+    fn revert_payload_is_not_a_confirmation(tx in tx_object()) {
+        let revert = ConfirmationPayload::revert(&tx);
+        let sender = ConfirmationPayload::new(&tx, ConfirmationRole::Sender);
+
+        prop_assert_eq!(&revert.tx_id, &tx.get_tx_id());
+        prop_assert_eq!(revert.role, ConfirmationRole::Sender);
+        prop_assert_ne!(revert.encode(), sender.encode());
+        prop_assert_ne!(revert.to_string(), sender.to_string());
+    }
+}
@@ -0,0 +1,54 @@
+//! `VaneError` codes are part of the RPC API and must not change between releases
+
+use jsonrpsee::types::error::ErrorObjectOwned;
+use primitives::{BlockchainNetwork, ConfirmationStatus, TxId, VaneError};
+
+fn tx_id() -> TxId {
+    serde_json::from_str("\"0x01\"").unwrap()
+}
+
+#[test]
+fn error_codes_are_stable() {
+    let codes = [
+        (VaneError::TxNotFound { tx_id: tx_id() }, -32001),
+        (
+            VaneError::ConfirmationUnavailable { tx_id: tx_id() },
+            -32002,
+        ),
+        (
+            VaneError::InvalidState {
+                tx_id: tx_id(),
+                expected: ConfirmationStatus::WaitingForSender,
+                actual: ConfirmationStatus::WaitingForReceiver,
+            },
+            -32003,
+        ),
+        (VaneError::TxExpired { tx_id: tx_id() }, -32004),
+        (VaneError::TxLocked { tx_id: tx_id() }, -32005),
+        (
+            VaneError::UnsupportedNetwork(BlockchainNetwork::Polkadot),
+            -32008,
+        ),
+        (VaneError::DeliveryNotFound { delivery_id: 7 }, -32011),
+        (VaneError::NotReceiver { tx_id: tx_id() }, -32012),
+        (VaneError::Storage("io".to_string()), -32090),
+    ];
+    for (err, code) in codes {
+        assert_eq!(err.code(), code, "{err:?}");
+    }
+}
+
+#[test]
+fn error_object_carries_the_error_as_data() {
+    let err = VaneError::NetworkMismatch {
+        tx_id: tx_id(),
+        expected: BlockchainNetwork::Polkadot,
+        actual: BlockchainNetwork::Kusama,
+    };
+    let object = ErrorObjectOwned::from(err.clone());
+
+    assert_eq!(object.code(), -32006);
+    assert_eq!(object.message(), err.to_string());
+    let data: VaneError = serde_json::from_str(object.data().unwrap().get()).unwrap();
+    assert_eq!(data, err);
+}